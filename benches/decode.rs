@@ -1,5 +1,5 @@
-use criterion::{criterion_group, criterion_main, Criterion, Throughput, BenchmarkId};
-use notepack::{NoteBuf, NoteParser, StringType, pack_note, pack_note_to_string};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use notepack::{NoteBuf, NoteParser, StringType, json_to_notepack, pack_note, pack_note_to_string};
 use std::hint::black_box;
 
 const CONTACTS_JSON: &str = include_str!("contact-list.json");
@@ -16,7 +16,7 @@ fn parse_iter_only(bytes: &[u8]) -> usize {
 
 fn bench_decode(c: &mut Criterion) {
     // One-time fixture setup outside the timer.
-    let json_len = CONTACTS_JSON.as_bytes().len() as u64;
+    let json_len = CONTACTS_JSON.len() as u64;
 
     let note_from_json: NoteBuf =
         serde_json::from_str(CONTACTS_JSON).expect("valid fixture");
@@ -117,7 +117,7 @@ fn bench_decode(c: &mut Criterion) {
                     serde_json::from_str(black_box(CONTACTS_JSON)).expect("json->note");
 
                 // apples-to-apples iterate comparison
-                let mut tags = note.tags; // move out; no need to clone
+                let tags = note.tags; // move out; no need to clone
                 let mut acc = 0usize;
                 for tag in tags {
                     for elem in tag {
@@ -131,5 +131,36 @@ fn bench_decode(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, bench_decode);
+fn bench_encode(c: &mut Criterion) {
+    let json_len = CONTACTS_JSON.len() as u64;
+
+    // 1) JSON -> NoteBuf -> notepack bytes
+    {
+        let mut group = c.benchmark_group("contacts/json_to_notebuf_to_notepack");
+        group.throughput(Throughput::Bytes(json_len));
+        group.bench_function("contacts.json.notebuf", |b| {
+            b.iter(|| {
+                let note: NoteBuf =
+                    serde_json::from_str(black_box(CONTACTS_JSON)).expect("json->note");
+                black_box(pack_note(&note).expect("pack ok"))
+            });
+        });
+        group.finish();
+    }
+
+    // 2) JSON -> notepack bytes directly
+    {
+        let mut group = c.benchmark_group("contacts/json_to_notepack");
+        group.throughput(Throughput::Bytes(json_len));
+        group.bench_function("contacts.json.direct", |b| {
+            b.iter(|| {
+                let packed = json_to_notepack(black_box(CONTACTS_JSON)).expect("transcode ok");
+                black_box(packed)
+            });
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_decode, bench_encode);
 criterion_main!(benches);
@@ -0,0 +1,354 @@
+use crate::{Error, Note, NoteBuf, NoteParser, pack_note};
+use std::io::Write;
+
+/// Magic bytes at the start and end of every notepack archive.
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"NPKA";
+
+/// Current archive version, written right after the leading [`ARCHIVE_MAGIC`].
+pub const ARCHIVE_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = ARCHIVE_MAGIC.len() + 1;
+const FOOTER_LEN: usize = 8 + 8 + ARCHIVE_MAGIC.len();
+const ENTRY_LEN: usize = 32 + 8 + 8;
+
+/// Writes a random-access notepack archive.
+///
+/// Records are streamed to the underlying writer as they are added; the id
+/// index is kept in memory and written by [`ArchiveWriter::finish`]. See
+/// `SPEC.md` §16 for the layout.
+///
+/// If the same id is added more than once, only the first record is indexed.
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{Archive, ArchiveWriter, NoteBuf};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     content: "hi".into(),
+///     ..NoteBuf::default()
+/// };
+///
+/// let mut writer = ArchiveWriter::new(Vec::new()).unwrap();
+/// writer.add_note(&note).unwrap();
+/// let bytes = writer.finish().unwrap();
+///
+/// let archive = Archive::new(&bytes).unwrap();
+/// let found = archive.get(&[0xaa; 32]).unwrap().expect("present");
+/// assert_eq!(found.content, "hi");
+/// ```
+#[derive(Debug)]
+pub struct ArchiveWriter<W: Write> {
+    inner: W,
+    offset: u64,
+    entries: Vec<([u8; 32], u64, u64)>,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    /// Create a writer and emit the archive header.
+    pub fn new(mut inner: W) -> Result<Self, Error> {
+        inner.write_all(&ARCHIVE_MAGIC)?;
+        inner.write_all(&[ARCHIVE_VERSION])?;
+        Ok(Self {
+            inner,
+            offset: HEADER_LEN as u64,
+            entries: Vec::new(),
+        })
+    }
+
+    /// Pack `note` and append it to the archive.
+    pub fn add_note(&mut self, note: &NoteBuf) -> Result<(), Error> {
+        let packed = pack_note(note)?;
+        self.add_packed(&packed)
+    }
+
+    /// Append an already-packed notepack payload to the archive.
+    ///
+    /// The payload is parsed to find its id, so malformed notes are rejected.
+    pub fn add_packed(&mut self, packed: &[u8]) -> Result<(), Error> {
+        let id = *NoteParser::new(packed).into_note()?.id;
+        self.inner.write_all(packed)?;
+        self.entries.push((id, self.offset, packed.len() as u64));
+        self.offset += packed.len() as u64;
+        Ok(())
+    }
+
+    /// Number of records added so far.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no records have been added yet.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write the sorted id index and footer, returning the underlying writer.
+    pub fn finish(mut self) -> Result<W, Error> {
+        // stable sort keeps the first record for duplicate ids
+        self.entries.sort_by_key(|e| e.0);
+        self.entries.dedup_by(|a, b| a.0 == b.0);
+
+        let index_offset = self.offset;
+        for (id, offset, len) in &self.entries {
+            self.inner.write_all(id)?;
+            self.inner.write_all(&offset.to_le_bytes())?;
+            self.inner.write_all(&len.to_le_bytes())?;
+        }
+
+        self.inner.write_all(&index_offset.to_le_bytes())?;
+        self.inner
+            .write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        self.inner.write_all(&ARCHIVE_MAGIC)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+/// A zero-copy reader over a notepack archive.
+///
+/// Lookups by id binary-search the trailing index, so they are O(log n) and
+/// never touch records other than the one returned. Returned [`Note`]s borrow
+/// directly from the archive bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Archive<'a> {
+    data: &'a [u8],
+    index: &'a [u8],
+}
+
+impl<'a> Archive<'a> {
+    /// Validate the header, footer and index bounds of an archive.
+    ///
+    /// Records themselves are not parsed until they are requested.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidArchive`] if the archive structure is malformed,
+    /// including an index whose ids are not strictly ascending, which would
+    /// make lookups silently miss entries.
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_LEN + FOOTER_LEN
+            || data[..ARCHIVE_MAGIC.len()] != ARCHIVE_MAGIC
+            || data[ARCHIVE_MAGIC.len()] != ARCHIVE_VERSION
+            || data[data.len() - ARCHIVE_MAGIC.len()..] != ARCHIVE_MAGIC
+        {
+            return Err(Error::InvalidArchive);
+        }
+
+        let footer = &data[data.len() - FOOTER_LEN..];
+        let index_offset = read_u64_le(&footer[0..8]);
+        let count = read_u64_le(&footer[8..16]);
+
+        let index_end = (data.len() - FOOTER_LEN) as u64;
+        let index_len = count
+            .checked_mul(ENTRY_LEN as u64)
+            .ok_or(Error::InvalidArchive)?;
+        if index_offset < HEADER_LEN as u64
+            || index_offset.checked_add(index_len) != Some(index_end)
+        {
+            return Err(Error::InvalidArchive);
+        }
+
+        let archive = Self {
+            data: &data[..index_offset as usize],
+            index: &data[index_offset as usize..index_end as usize],
+        };
+        if (1..archive.len()).any(|i| archive.entry_id(i - 1) >= archive.entry_id(i)) {
+            return Err(Error::InvalidArchive);
+        }
+
+        Ok(archive)
+    }
+
+    /// Number of notes in the archive.
+    pub fn len(&self) -> usize {
+        self.index.len() / ENTRY_LEN
+    }
+
+    /// Whether the archive holds no notes.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Look up a note by its 32-byte id.
+    pub fn get(&self, id: &[u8; 32]) -> Result<Option<Note<'a>>, Error> {
+        match self.find(id) {
+            Some(i) => self.note_at(i).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether a note with this id is present, without parsing it.
+    pub fn contains(&self, id: &[u8; 32]) -> bool {
+        self.find(id).is_some()
+    }
+
+    /// Binary-search the index for `id`, returning its position.
+    pub fn find(&self, id: &[u8; 32]) -> Option<usize> {
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.entry_id(mid).cmp(id) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    /// The raw notepack payload of the `i`-th note in id order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidArchive`] if `i` is out of range or the index
+    /// entry points outside the records.
+    pub fn packed_at(&self, i: usize) -> Result<&'a [u8], Error> {
+        if i >= self.len() {
+            return Err(Error::InvalidArchive);
+        }
+        let entry = &self.index[i * ENTRY_LEN..(i + 1) * ENTRY_LEN];
+        let offset = read_u64_le(&entry[32..40]);
+        let len = read_u64_le(&entry[40..48]);
+
+        let end = offset.checked_add(len).ok_or(Error::InvalidArchive)?;
+        if offset < HEADER_LEN as u64 || end > self.data.len() as u64 {
+            return Err(Error::InvalidArchive);
+        }
+        Ok(&self.data[offset as usize..end as usize])
+    }
+
+    /// Parse the `i`-th note in id order.
+    pub fn note_at(&self, i: usize) -> Result<Note<'a>, Error> {
+        NoteParser::new(self.packed_at(i)?).into_note()
+    }
+
+    /// Iterate over all notes in id order.
+    pub fn iter(&self) -> impl Iterator<Item = Result<Note<'a>, Error>> + 'a {
+        let archive = *self;
+        (0..archive.len()).map(move |i| archive.note_at(i))
+    }
+
+    fn entry_id(&self, i: usize) -> &'a [u8; 32] {
+        self.index[i * ENTRY_LEN..i * ENTRY_LEN + 32]
+            .try_into()
+            .expect("entry length checked")
+    }
+}
+
+fn read_u64_le(bs: &[u8]) -> u64 {
+    u64::from_le_bytes(bs.try_into().expect("8 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn note(id_byte: u8, content: &str) -> NoteBuf {
+        NoteBuf {
+            id: hex::encode([id_byte; 32]),
+            content: content.into(),
+            ..test_util::note()
+        }
+    }
+
+    fn build(notes: &[NoteBuf]) -> Vec<u8> {
+        let mut writer = ArchiveWriter::new(Vec::new()).expect("writer");
+        for n in notes {
+            writer.add_note(n).expect("add");
+        }
+        writer.finish().expect("finish")
+    }
+
+    #[test]
+    fn lookup_by_id() -> Result<(), Error> {
+        let notes: Vec<_> = [9u8, 3, 200, 7, 42]
+            .iter()
+            .map(|&b| note(b, &format!("note {b}")))
+            .collect();
+        let bytes = build(&notes);
+        let archive = Archive::new(&bytes)?;
+
+        assert_eq!(archive.len(), 5);
+        for &b in &[9u8, 3, 200, 7, 42] {
+            let n = archive.get(&[b; 32])?.expect("present");
+            assert_eq!(n.content, format!("note {b}"));
+            assert!(archive.contains(&[b; 32]));
+        }
+        assert!(archive.get(&[1; 32])?.is_none());
+        assert!(!archive.contains(&[255; 32]));
+
+        assert!(matches!(archive.packed_at(5), Err(Error::InvalidArchive)));
+        assert!(matches!(
+            archive.note_at(usize::MAX),
+            Err(Error::InvalidArchive)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn iter_is_sorted_and_deduped() -> Result<(), Error> {
+        let bytes = build(&[note(5, "first"), note(1, "one"), note(5, "second")]);
+        let archive = Archive::new(&bytes)?;
+
+        let got: Vec<_> = archive
+            .iter()
+            .map(|n| n.map(|n| (n.id[0], n.content)))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(got, vec![(1, "one"), (5, "first")]);
+        Ok(())
+    }
+
+    #[test]
+    fn empty_archive() -> Result<(), Error> {
+        let bytes = build(&[]);
+        let archive = Archive::new(&bytes)?;
+        assert!(archive.is_empty());
+        assert!(archive.get(&[0; 32])?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_corrupt_archives() {
+        let bytes = build(&[note(1, "x")]);
+
+        assert!(matches!(
+            Archive::new(&bytes[..10]),
+            Err(Error::InvalidArchive)
+        ));
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(
+            Archive::new(&bad_magic),
+            Err(Error::InvalidArchive)
+        ));
+
+        // claim more index entries than fit
+        let mut bad_count = bytes.clone();
+        let pos = bad_count.len() - FOOTER_LEN + 8;
+        bad_count[pos..pos + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            Archive::new(&bad_count),
+            Err(Error::InvalidArchive)
+        ));
+
+        // swap the two index entries so the ids are out of order
+        let bytes = build(&[note(1, "x"), note(2, "y")]);
+        let index = bytes.len() - FOOTER_LEN - 2 * ENTRY_LEN;
+        let mut unsorted = bytes.clone();
+        unsorted[index..index + ENTRY_LEN]
+            .copy_from_slice(&bytes[index + ENTRY_LEN..index + 2 * ENTRY_LEN]);
+        unsorted[index + ENTRY_LEN..index + 2 * ENTRY_LEN]
+            .copy_from_slice(&bytes[index..index + ENTRY_LEN]);
+        assert!(matches!(
+            Archive::new(&unsorted),
+            Err(Error::InvalidArchive)
+        ));
+    }
+}
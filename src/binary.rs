@@ -0,0 +1,296 @@
+use crate::stringtype::{StringType, StringTypeBuf};
+use crate::{
+    EncodeOptions, Error, Note, NoteBuf, NoteEncoder, NoteParser, decode_fixed_hex, write_hex_bytes,
+};
+
+/// An owned note with binary fixed fields and typed tag elements.
+///
+/// This is the in-memory counterpart of the notepack format: `id`, `pubkey`
+/// and `sig` are byte arrays and each tag element remembers whether it is
+/// text or bytes, so converting to and from packed bytes or a [`Note`] never
+/// touches hex. [`NoteBuf`], by contrast, keeps everything as hex strings.
+///
+/// Conversions are lossless: a [`Note`] keeps every element's [`StringType`],
+/// and a `NoteBuf` converts back to the same strings it came from. The one
+/// exception is an empty [`StringTypeBuf::Bytes`] element, which notepack
+/// cannot represent: [`BinaryNote::to_packed`] writes it as an empty string,
+/// so it comes back from [`BinaryNote::from_packed`] as `Str("")`.
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{BinaryNote, NoteBuf, StringTypeBuf, pack_note};
+///
+/// let buf = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     tags: vec![vec!["p".into(), "dd".repeat(32)]],
+///     ..NoteBuf::default()
+/// };
+///
+/// let note = BinaryNote::try_from(&buf).unwrap();
+/// assert_eq!(note.pubkey, [0xbb; 32]);
+/// assert_eq!(note.tags[0][1], StringTypeBuf::Bytes(vec![0xdd; 32]));
+///
+/// let packed = note.to_packed();
+/// assert_eq!(packed, pack_note(&buf).unwrap());
+/// assert_eq!(BinaryNote::from_packed(&packed).unwrap(), note);
+/// assert_eq!(NoteBuf::from(&note).tags, buf.tags);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryNote {
+    /// 32-bytes sha256 of the the serialized event data
+    pub id: [u8; 32],
+    /// 32-bytes public key of the event creator
+    pub pubkey: [u8; 32],
+    /// 64-bytes signature of the id
+    pub sig: [u8; 64],
+    /// unix timestamp in seconds
+    pub created_at: u64,
+    /// event kind
+    pub kind: u64,
+    /// tags, with each element stored as text or bytes
+    pub tags: Vec<Vec<StringTypeBuf>>,
+    /// arbitrary string
+    pub content: String,
+}
+
+impl Default for BinaryNote {
+    fn default() -> Self {
+        Self {
+            id: [0; 32],
+            pubkey: [0; 32],
+            sig: [0; 64],
+            created_at: 0,
+            kind: 0,
+            tags: Vec::new(),
+            content: String::new(),
+        }
+    }
+}
+
+impl BinaryNote {
+    /// Decode a packed note.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`Error`] from [`NoteParser::into_note`], or from the first
+    /// malformed tag element.
+    pub fn from_packed(packed: &[u8]) -> Result<Self, Error> {
+        Self::try_from(&NoteParser::new(packed).into_note()?)
+    }
+
+    /// Encode as notepack, keeping each tag element's type.
+    ///
+    /// An empty [`StringTypeBuf::Bytes`] element is written as an empty string.
+    pub fn to_packed(&self) -> Vec<u8> {
+        let mut enc = NoteEncoder::begin(
+            &self.id,
+            &self.pubkey,
+            &self.sig,
+            self.created_at,
+            self.kind,
+        );
+        enc.content(&self.content);
+        for tag in &self.tags {
+            enc.begin_tag();
+            for elem in tag {
+                match elem {
+                    StringTypeBuf::Bytes(bs) => enc.elem_bytes(bs),
+                    StringTypeBuf::Str(s) => enc.elem_str(s),
+                };
+            }
+        }
+        enc.finish()
+    }
+}
+
+impl TryFrom<&Note<'_>> for BinaryNote {
+    type Error = Error;
+
+    fn try_from(note: &Note<'_>) -> Result<Self, Error> {
+        let mut tags = Vec::new();
+        let mut iter = note.tags.clone();
+        while let Some(elems) = iter.next_tag()? {
+            tags.push(
+                elems
+                    .map(|elem| elem.map(StringTypeBuf::from))
+                    .collect::<Result<_, _>>()?,
+            );
+        }
+
+        Ok(Self {
+            id: *note.id,
+            pubkey: *note.pubkey,
+            sig: *note.sig,
+            created_at: note.created_at,
+            kind: note.kind,
+            tags,
+            content: note.content.to_string(),
+        })
+    }
+}
+
+impl TryFrom<Note<'_>> for BinaryNote {
+    type Error = Error;
+
+    fn try_from(note: Note<'_>) -> Result<Self, Error> {
+        Self::try_from(&note)
+    }
+}
+
+/// Hex tag elements become [`StringTypeBuf::Bytes`] exactly where
+/// [`pack_note`](crate::pack_note) would store them as bytes.
+impl TryFrom<&NoteBuf> for BinaryNote {
+    type Error = Error;
+
+    fn try_from(note: &NoteBuf) -> Result<Self, Error> {
+        // same order as pack_note, so both report the same error
+        let id = decode_fixed_hex(&note.id, Error::InvalidId)?;
+        let pubkey = decode_fixed_hex(&note.pubkey, Error::InvalidPubkey)?;
+        let sig = decode_fixed_hex(&note.sig, Error::InvalidSig)?;
+
+        let options = EncodeOptions::DEFAULT;
+        let tags = note
+            .tags
+            .iter()
+            .map(|tag| {
+                let name = tag.first().map_or("", String::as_str);
+                tag.iter()
+                    .enumerate()
+                    .map(|(i, elem)| {
+                        if !options.stores_as_bytes(name, i, elem) {
+                            return StringTypeBuf::Str(elem.clone());
+                        }
+                        let mut bytes = Vec::with_capacity(elem.len() / 2);
+                        write_hex_bytes(&mut |bs| bytes.extend_from_slice(bs), elem);
+                        StringTypeBuf::Bytes(bytes)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            id,
+            pubkey,
+            sig,
+            created_at: note.created_at,
+            kind: note.kind,
+            tags,
+            content: note.content.clone(),
+        })
+    }
+}
+
+impl From<&BinaryNote> for NoteBuf {
+    fn from(note: &BinaryNote) -> Self {
+        let tags = note
+            .tags
+            .iter()
+            .map(|tag| {
+                tag.iter()
+                    .map(|elem| match elem.as_string_type() {
+                        StringType::Bytes(bs) => hex::encode(bs),
+                        StringType::Str(s) => s.to_string(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        NoteBuf {
+            id: hex::encode(note.id),
+            pubkey: hex::encode(note.pubkey),
+            created_at: note.created_at,
+            kind: note.kind,
+            tags,
+            content: note.content.clone(),
+            sig: hex::encode(note.sig),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pack_note, pack_note_with, test_util};
+
+    fn note_buf() -> NoteBuf {
+        NoteBuf {
+            created_at: 1_720_000_000,
+            kind: 30_023,
+            tags: vec![
+                vec!["e".into(), "aa".repeat(32), "wss://relay".into()],
+                vec![],
+                vec!["t".into(), "".into(), "cafe".into(), "ABCD".into()],
+            ],
+            content: "é".repeat(100),
+            ..test_util::note()
+        }
+    }
+
+    #[test]
+    fn conversions_round_trip() -> Result<(), Error> {
+        let buf = note_buf();
+        let note = BinaryNote::try_from(&buf)?;
+        assert_eq!(
+            note.tags[2],
+            [
+                StringTypeBuf::Str("t".into()),
+                StringTypeBuf::Str("".into()),
+                StringTypeBuf::Bytes(vec![0xca, 0xfe]),
+                StringTypeBuf::Str("ABCD".into()),
+            ]
+        );
+
+        let back = NoteBuf::from(&note);
+        assert_eq!(back.tags, buf.tags);
+        assert_eq!(pack_note(&back)?, pack_note(&buf)?);
+
+        let packed = note.to_packed();
+        assert_eq!(packed, pack_note(&buf)?);
+        assert_eq!(BinaryNote::from_packed(&packed)?, note);
+        Ok(())
+    }
+
+    #[test]
+    fn packed_element_types_are_kept() -> Result<(), Error> {
+        let packed = pack_note_with(&note_buf(), &EncodeOptions::NO_HEX_COMPRESSION)?;
+        let note = BinaryNote::from_packed(&packed)?;
+        assert_eq!(note.tags[2][2], StringTypeBuf::Str("cafe".into()));
+        assert_eq!(note.to_packed(), packed);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let mut buf = note_buf();
+        buf.sig.pop();
+        assert!(matches!(BinaryNote::try_from(&buf), Err(Error::InvalidSig)));
+
+        let packed = pack_note(&note_buf()).expect("pack");
+        let err = BinaryNote::from_packed(&packed[..packed.len() - 1]).unwrap_err();
+        assert!(matches!(err.root(), Error::Truncated));
+
+        // fixed fields are checked in the same order as pack_note
+        let mut buf = note_buf();
+        buf.pubkey.pop();
+        buf.sig.pop();
+        assert!(matches!(pack_note(&buf), Err(Error::InvalidPubkey)));
+        assert!(matches!(
+            BinaryNote::try_from(&buf),
+            Err(Error::InvalidPubkey)
+        ));
+    }
+
+    #[test]
+    fn empty_bytes_come_back_as_text() -> Result<(), Error> {
+        let note = BinaryNote {
+            tags: vec![vec![StringTypeBuf::Bytes(vec![])]],
+            ..BinaryNote::default()
+        };
+        let back = BinaryNote::try_from(NoteParser::new(&note.to_packed()).into_note()?)?;
+        assert_eq!(back.tags, [[StringTypeBuf::Str("".into())]]);
+        Ok(())
+    }
+}
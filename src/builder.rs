@@ -0,0 +1,206 @@
+use crate::canonical::write_canonical;
+use crate::{Error, NoteBuf, pack_note};
+use secp256k1::{Keypair, SECP256K1, SecretKey};
+use sha2::{Digest, Sha256};
+
+/// Builds and signs new Nostr notes.
+///
+/// Collects kind, content and tags, then [`NoteBuilder::sign`] computes the
+/// NIP-01 id, signs it with BIP-340 Schnorr and returns a finished [`NoteBuf`].
+/// [`NoteBuilder::sign_packed`] additionally returns the notepack bytes.
+///
+/// Requires the `secp256k1` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuilder, NoteParser};
+/// use notepack::secp256k1::SecretKey;
+///
+/// let seckey = SecretKey::from_byte_array(&[0x01; 32]).unwrap();
+/// let (note, packed) = NoteBuilder::new(1)
+///     .content("hello")
+///     .t("nostr")
+///     .created_at(1753898766)
+///     .sign_packed(&seckey)
+///     .unwrap();
+///
+/// let parsed = NoteParser::new(&packed).into_note().unwrap();
+/// assert_eq!(hex::encode(parsed.id), note.id);
+/// assert!(parsed.verify_id().unwrap());
+/// assert!(parsed.verify_signature().unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct NoteBuilder {
+    kind: u64,
+    content: String,
+    created_at: Option<u64>,
+    tags: Vec<Vec<String>>,
+}
+
+impl NoteBuilder {
+    /// Start a new note of the given kind with empty content and no tags.
+    pub fn new(kind: u64) -> Self {
+        Self {
+            kind,
+            content: String::new(),
+            created_at: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Set the note content.
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = content.into();
+        self
+    }
+
+    /// Set `created_at`. Defaults to the current time when signing.
+    pub fn created_at(mut self, created_at: u64) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Append an arbitrary tag.
+    pub fn tag<I, S>(mut self, elems: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags.push(elems.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Append an `["e", <event id>]` tag.
+    pub fn e(self, id: &[u8; 32]) -> Self {
+        self.tag(["e".to_string(), hex::encode(id)])
+    }
+
+    /// Append a `["p", <pubkey>]` tag.
+    pub fn p(self, pubkey: &[u8; 32]) -> Self {
+        self.tag(["p".to_string(), hex::encode(pubkey)])
+    }
+
+    /// Append an `["a", "<kind>:<pubkey>:<d>"]` tag referencing an addressable event.
+    pub fn a(self, kind: u64, pubkey: &[u8; 32], d: &str) -> Self {
+        self.tag([
+            "a".to_string(),
+            format!("{kind}:{}:{d}", hex::encode(pubkey)),
+        ])
+    }
+
+    /// Append a `["t", <hashtag>]` tag.
+    pub fn t(self, hashtag: &str) -> Self {
+        self.tag(["t", hashtag])
+    }
+
+    /// Append a `["d", <identifier>]` tag.
+    pub fn d(self, identifier: &str) -> Self {
+        self.tag(["d", identifier])
+    }
+
+    /// Compute the id, sign it with `seckey` and return the finished note.
+    pub fn sign(self, seckey: &SecretKey) -> Result<NoteBuf, Error> {
+        let keypair = Keypair::from_secret_key(SECP256K1, seckey);
+        let pubkey = keypair.x_only_public_key().0.serialize();
+        let created_at = self.created_at.unwrap_or_else(now);
+
+        let mut hasher = Sha256::new();
+        write_canonical(
+            &mut |bs| hasher.update(bs),
+            &pubkey,
+            created_at,
+            self.kind,
+            self.tags.as_slice(),
+            &self.content,
+        )?;
+        let id: [u8; 32] = hasher.finalize().into();
+        let sig = keypair.sign_schnorr(&id);
+
+        Ok(NoteBuf {
+            id: hex::encode(id),
+            pubkey: hex::encode(pubkey),
+            created_at,
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+            sig: hex::encode(sig.to_byte_array()),
+        })
+    }
+
+    /// Like [`NoteBuilder::sign`], but also returns the [`pack_note`] payload.
+    pub fn sign_packed(self, seckey: &SecretKey) -> Result<(NoteBuf, Vec<u8>), Error> {
+        let note = self.sign(seckey)?;
+        let packed = pack_note(&note)?;
+        Ok((note, packed))
+    }
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoteParser, StringType};
+
+    fn seckey() -> SecretKey {
+        SecretKey::from_byte_array(&[0x42; 32]).expect("seckey")
+    }
+
+    #[test]
+    fn sign_produces_verifiable_note() -> Result<(), Error> {
+        let (note, packed) = NoteBuilder::new(1)
+            .content("gm \"nostr\"\n")
+            .e(&[0xaa; 32])
+            .p(&[0xbb; 32])
+            .created_at(1_700_000_000)
+            .sign_packed(&seckey())?;
+
+        let parsed = NoteParser::new(&packed).into_note()?;
+        assert_eq!(hex::encode(parsed.id), note.id);
+        assert_eq!(hex::encode(parsed.pubkey), note.pubkey);
+        assert_eq!(parsed.created_at, 1_700_000_000);
+        assert!(parsed.verify_id()?);
+        assert!(parsed.verify_signature()?);
+        Ok(())
+    }
+
+    #[test]
+    fn typed_tag_helpers() -> Result<(), Error> {
+        let pk = [0xcc; 32];
+        let note = NoteBuilder::new(30023)
+            .d("my-article")
+            .a(30023, &pk, "other")
+            .t("cafe")
+            .tag(["r", "https://example.com"])
+            .sign(&seckey())?;
+
+        assert_eq!(
+            note.tags,
+            vec![
+                vec!["d".to_string(), "my-article".to_string()],
+                vec!["a".to_string(), format!("30023:{}:other", hex::encode(pk))],
+                vec!["t".to_string(), "cafe".to_string()],
+                vec!["r".to_string(), "https://example.com".to_string()],
+            ]
+        );
+
+        // "cafe" packs as bytes but the id must still verify after decoding
+        let packed = pack_note(&note)?;
+        let parsed = NoteParser::new(&packed).into_note()?;
+        let mut tags = parsed.tags.clone();
+        tags.next_tag()?;
+        tags.next_tag()?;
+        let mut t = tags.next_tag()?.expect("t tag");
+        t.next();
+        assert!(matches!(t.next(), Some(Ok(StringType::Bytes(_)))));
+        drop(t);
+        assert!(parsed.verify_id()?);
+        Ok(())
+    }
+}
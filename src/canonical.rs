@@ -0,0 +1,237 @@
+use crate::Error;
+use crate::note::{TagEvent, Tags};
+use crate::stringtype::StringType;
+
+/// A tags representation that can be written in canonical NIP-01 form.
+pub(crate) trait CanonicalTags {
+    fn write_tags(&self, out: &mut impl FnMut(&[u8])) -> Result<(), Error>;
+}
+
+impl CanonicalTags for Tags<'_> {
+    fn write_tags(&self, out: &mut impl FnMut(&[u8])) -> Result<(), Error> {
+        write_tags_json(out, self, write_json_str)
+    }
+}
+
+/// Write `tags` as a JSON array of arrays, escaping text elements with
+/// `write_str` and hex-encoding [`StringType::Bytes`] elements.
+///
+/// Shared by the NIP-01 serialization and the plain JSON writer in
+/// `json.rs`, which differ only in how strings are escaped.
+pub(crate) fn write_tags_json<O: FnMut(&[u8])>(
+    out: &mut O,
+    tags: &Tags<'_>,
+    write_str: fn(&mut O, &str),
+) -> Result<(), Error> {
+    out(b"[");
+    tags.walk(|event| match event {
+        TagEvent::Start { tag, .. } => {
+            if tag > 0 {
+                out(b",");
+            }
+            out(b"[");
+        }
+        TagEvent::Elem { elem, value } => {
+            if elem > 0 {
+                out(b",");
+            }
+            match value {
+                StringType::Str(s) => write_str(out, s),
+                StringType::Bytes(bs) => {
+                    out(b"\"");
+                    write_hex(out, bs);
+                    out(b"\"");
+                }
+            }
+        }
+        TagEvent::End => out(b"]"),
+    })?;
+    out(b"]");
+    Ok(())
+}
+
+impl CanonicalTags for [Vec<String>] {
+    fn write_tags(&self, out: &mut impl FnMut(&[u8])) -> Result<(), Error> {
+        out(b"[");
+        for (i, tag) in self.iter().enumerate() {
+            if i > 0 {
+                out(b",");
+            }
+            out(b"[");
+            for (j, elem) in tag.iter().enumerate() {
+                if j > 0 {
+                    out(b",");
+                }
+                write_json_str(out, elem);
+            }
+            out(b"]");
+        }
+        out(b"]");
+        Ok(())
+    }
+}
+
+/// Write the canonical NIP-01 serialization of an event:
+///
+/// `[0,"<pubkey>",<created_at>,<kind>,<tags>,"<content>"]`
+///
+/// Output is emitted in chunks to `out`, so the caller decides whether it
+/// lands in a hasher, a buffer or somewhere else. [`StringType::Bytes`] tag
+/// elements are hex-encoded (lowercase) on the fly.
+pub(crate) fn write_canonical(
+    out: &mut impl FnMut(&[u8]),
+    pubkey: &[u8; 32],
+    created_at: u64,
+    kind: u64,
+    tags: &(impl CanonicalTags + ?Sized),
+    content: &str,
+) -> Result<(), Error> {
+    out(b"[0,\"");
+    write_hex(out, pubkey);
+    out(b"\",");
+    write_u64(out, created_at);
+    out(b",");
+    write_u64(out, kind);
+    out(b",");
+    tags.write_tags(out)?;
+    out(b",");
+    write_json_str(out, content);
+    out(b"]");
+
+    Ok(())
+}
+
+/// Write `s` as a JSON string literal using the NIP-01 escaping rules.
+///
+/// Only `\n`, `"`, `\`, `\r`, `\t`, backspace and form feed are escaped;
+/// everything else is written verbatim.
+pub(crate) fn write_json_str(out: &mut impl FnMut(&[u8]), s: &str) {
+    out(b"\"");
+
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        let escaped: &[u8] = match b {
+            b'\n' => b"\\n",
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0x08 => b"\\b",
+            0x0c => b"\\f",
+            _ => continue,
+        };
+        if start < i {
+            out(&bytes[start..i]);
+        }
+        out(escaped);
+        start = i + 1;
+    }
+    if start < bytes.len() {
+        out(&bytes[start..]);
+    }
+
+    out(b"\"");
+}
+
+/// Lowercase hex-encode `bs` without allocating.
+pub(crate) fn write_hex(out: &mut impl FnMut(&[u8]), bs: &[u8]) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut chunk = [0u8; 64];
+
+    for part in bs.chunks(chunk.len() / 2) {
+        for (i, b) in part.iter().enumerate() {
+            chunk[i * 2] = HEX[(b >> 4) as usize];
+            chunk[i * 2 + 1] = HEX[(b & 0x0f) as usize];
+        }
+        out(&chunk[..part.len() * 2]);
+    }
+}
+
+/// Write `n` in decimal without allocating.
+pub(crate) fn write_u64(out: &mut impl FnMut(&[u8]), mut n: u64) {
+    let mut digits = [0u8; 20];
+    let mut i = digits.len();
+    loop {
+        i -= 1;
+        digits[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    out(&digits[i..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoteBuf, NoteParser, pack_note};
+
+    fn canonical_string(note: &NoteBuf) -> String {
+        let packed = pack_note(note).expect("pack ok");
+        let note = NoteParser::new(&packed).into_note().expect("parse ok");
+
+        let mut buf = Vec::new();
+        write_canonical(
+            &mut |bs| buf.extend_from_slice(bs),
+            note.pubkey,
+            note.created_at,
+            note.kind,
+            &note.tags,
+            note.content,
+        )
+        .expect("canonical ok");
+        String::from_utf8(buf).expect("utf8")
+    }
+
+    #[test]
+    fn canonical_matches_nip01_layout() {
+        let note = NoteBuf {
+            id: "00".repeat(32),
+            pubkey: "11".repeat(32),
+            created_at: 1720000000,
+            kind: 1,
+            tags: vec![
+                vec![
+                    "e".into(),
+                    "aa".repeat(32),
+                    "wss://relay.example.com".into(),
+                ],
+                vec!["t".into(), "nostr".into()],
+            ],
+            content: "line\nquote\" back\\ tab\t\u{8}\u{c}\r é".into(),
+            sig: "22".repeat(64),
+        };
+
+        let expected = format!(
+            "[0,\"{}\",1720000000,1,[[\"e\",\"{}\",\"wss://relay.example.com\"],[\"t\",\"nostr\"]],\"line\\nquote\\\" back\\\\ tab\\t\\b\\f\\r é\"]",
+            "11".repeat(32),
+            "aa".repeat(32),
+        );
+
+        assert_eq!(canonical_string(&note), expected);
+    }
+
+    #[test]
+    fn canonical_writes_other_control_chars_verbatim() {
+        let mut buf = Vec::new();
+        write_json_str(&mut |bs| buf.extend_from_slice(bs), "a\u{1}b\u{7f}");
+        assert_eq!(buf, b"\"a\x01b\x7f\"");
+    }
+
+    #[test]
+    fn canonical_empty_tags_and_zero_values() {
+        let note = NoteBuf {
+            id: "00".repeat(32),
+            pubkey: "ab".repeat(32),
+            sig: "00".repeat(64),
+            ..NoteBuf::default()
+        };
+
+        assert_eq!(
+            canonical_string(&note),
+            format!("[0,\"{}\",0,0,[],\"\"]", "ab".repeat(32))
+        );
+    }
+}
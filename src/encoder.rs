@@ -0,0 +1,271 @@
+use crate::parser::NOTEPACK_VERSION;
+use crate::varint::encode_varint;
+use crate::{EncodeOptions, put_elem, put_varint};
+
+/// A low-level encoder that writes a notepack payload field by field.
+///
+/// Tags and their elements are appended one at a time straight into the
+/// output buffer. Each count gets a one-byte slot that is backpatched when
+/// the tag (or, for `num_tags`, the note) is finished; only a count of 128 or
+/// more needs a wider varint, in which case the bytes after the slot are
+/// shifted over in place. Producers that generate tags on the fly therefore
+/// don't have to collect a `Vec<Vec<String>>` for
+/// [`pack_note`](crate::pack_note) first.
+///
+/// [`NoteEncoder::content`] may be called at any point before `finish`;
+/// without it the content is empty. Setting it after tags have been written
+/// shifts them over to make room.
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, NoteEncoder, pack_note};
+///
+/// let follows = [[0x01; 32], [0x02; 32]];
+///
+/// let mut enc = NoteEncoder::begin(&[0xaa; 32], &[0xbb; 32], &[0xcc; 64], 1753898766, 3);
+/// for pubkey in &follows {
+///     enc.begin_tag().elem_str("p").elem_bytes(pubkey);
+/// }
+/// let packed = enc.finish();
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     created_at: 1753898766,
+///     kind: 3,
+///     tags: follows.iter().map(|pk| vec!["p".into(), hex::encode(pk)]).collect(),
+///     content: String::new(),
+/// };
+/// assert_eq!(packed, pack_note(&note).unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct NoteEncoder {
+    buf: Vec<u8>,            // the payload so far
+    header_len: usize,       // version through kind
+    tags_slot: usize,        // one-byte slot for num_tags, right after content
+    num_tags: u64,           // finished tags
+    tag_slot: Option<usize>, // one-byte slot for the open tag's num_elems
+    num_elems: u64,          // elements in the open tag
+}
+
+impl NoteEncoder {
+    /// Start a note with its fixed-size fields.
+    pub fn begin(
+        id: &[u8; 32],
+        pubkey: &[u8; 32],
+        sig: &[u8; 64],
+        created_at: u64,
+        kind: u64,
+    ) -> Self {
+        let mut buf = Vec::new();
+        let mut out = |bs: &[u8]| buf.extend_from_slice(bs);
+        put_varint(&mut out, NOTEPACK_VERSION as u64);
+        out(id);
+        out(pubkey);
+        out(sig);
+        put_varint(&mut out, created_at);
+        put_varint(&mut out, kind);
+
+        let header_len = buf.len();
+        // empty content, then the num_tags slot
+        buf.extend_from_slice(&[0, 0]);
+        Self {
+            buf,
+            header_len,
+            tags_slot: header_len + 1,
+            num_tags: 0,
+            tag_slot: None,
+            num_elems: 0,
+        }
+    }
+
+    /// Set the content, replacing any set earlier.
+    pub fn content(&mut self, content: &str) -> &mut Self {
+        let mut len = [0u8; 10];
+        let len = encode_varint(&mut len, content.len() as u64);
+        let new_end = self.header_len + len.len() + content.len();
+
+        self.buf.splice(
+            self.header_len..self.tags_slot,
+            len.iter().chain(content.as_bytes()).copied(),
+        );
+        if let Some(slot) = &mut self.tag_slot {
+            *slot = *slot - self.tags_slot + new_end;
+        }
+        self.tags_slot = new_end;
+        self
+    }
+
+    /// Close the current tag, if any, and start a new one.
+    pub fn begin_tag(&mut self) -> &mut Self {
+        self.end_tag();
+        self.tag_slot = Some(self.buf.len());
+        self.buf.push(0);
+        self
+    }
+
+    /// Append a text element to the current tag, stored as-is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no tag has been started with [`NoteEncoder::begin_tag`].
+    pub fn elem_str(&mut self, s: &str) -> &mut Self {
+        self.elem_header(s.len(), false);
+        self.buf.extend_from_slice(s.as_bytes());
+        self
+    }
+
+    /// Append a raw bytes element to the current tag.
+    ///
+    /// An empty `bs` is stored as an empty text element, since canonical
+    /// notepack never contains empty bytes elements (SPEC §5).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no tag has been started with [`NoteEncoder::begin_tag`].
+    pub fn elem_bytes(&mut self, bs: &[u8]) -> &mut Self {
+        if bs.is_empty() {
+            return self.elem_str("");
+        }
+        self.elem_header(bs.len(), true);
+        self.buf.extend_from_slice(bs);
+        self
+    }
+
+    /// Append an element the way [`pack_note`](crate::pack_note) would:
+    /// lowercase hex is stored as bytes, anything else as text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no tag has been started with [`NoteEncoder::begin_tag`].
+    pub fn elem(&mut self, s: &str) -> &mut Self {
+        self.next_elem();
+        // the default options don't look at the tag name or position
+        let as_bytes = EncodeOptions::DEFAULT.stores_as_bytes("", 0, s);
+        put_elem(&mut |bs| self.buf.extend_from_slice(bs), s, as_bytes);
+        self
+    }
+
+    /// Close the current tag and return the finished payload.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.end_tag();
+        patch_varint(&mut self.buf, self.tags_slot, self.num_tags);
+        self.buf
+    }
+
+    fn elem_header(&mut self, len: usize, is_bytes: bool) {
+        self.next_elem();
+        put_varint(
+            &mut |bs| self.buf.extend_from_slice(bs),
+            (len as u64) << 1 | is_bytes as u64,
+        );
+    }
+
+    /// Count an element about to be written to the open tag.
+    fn next_elem(&mut self) {
+        assert!(
+            self.tag_slot.is_some(),
+            "NoteEncoder: element written before begin_tag"
+        );
+        self.num_elems += 1;
+    }
+
+    /// Fill in the open tag's element count.
+    fn end_tag(&mut self) {
+        let Some(slot) = self.tag_slot.take() else {
+            return;
+        };
+        patch_varint(&mut self.buf, slot, self.num_elems);
+        self.num_tags += 1;
+        self.num_elems = 0;
+    }
+}
+
+/// Write `n` over the one-byte slot at `at`, shifting what follows if it
+/// needs more than one byte.
+fn patch_varint(buf: &mut Vec<u8>, at: usize, n: u64) {
+    let mut tmp = [0u8; 10];
+    let bs = encode_varint(&mut tmp, n);
+    if let [b] = bs {
+        buf[at] = *b;
+    } else {
+        buf.splice(at..at + 1, bs.iter().copied());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoteBuf, NoteParser, pack_note, test_util};
+
+    #[test]
+    fn matches_pack_note() {
+        let note = NoteBuf {
+            created_at: 1_720_000_000,
+            kind: 30_023,
+            tags: vec![
+                vec!["e".into(), "aa".repeat(32), "wss://relay".into()],
+                vec![],
+                vec!["t".into(), "".into(), "cafe".into(), "x".repeat(200)],
+                // needs a two-byte num_elems
+                (0..200).map(|i| i.to_string()).collect(),
+                vec!["p".into(), "bb".repeat(32)],
+            ],
+            content: "é".repeat(100),
+            ..test_util::note()
+        };
+
+        let mut enc =
+            NoteEncoder::begin(&[0x01; 32], &[0x02; 32], &[0x03; 64], 1_720_000_000, 30_023);
+        // tags may come before the content
+        for tag in &note.tags {
+            enc.begin_tag();
+            for elem in tag {
+                enc.elem(elem);
+            }
+        }
+        enc.content("replaced").content(&note.content);
+
+        assert_eq!(enc.finish(), pack_note(&note).expect("pack"));
+    }
+
+    #[test]
+    fn empty_note_matches_pack_note() {
+        let note = test_util::note();
+        let enc = NoteEncoder::begin(&[0x01; 32], &[0x02; 32], &[0x03; 64], 0, 0);
+        assert_eq!(enc.finish(), pack_note(&note).expect("pack"));
+    }
+
+    #[test]
+    fn many_tags_and_empty_bytes_stay_canonical() {
+        let note = NoteBuf {
+            tags: (0..300)
+                .map(|i| vec!["t".into(), "".into(), i.to_string()])
+                .collect(),
+            ..test_util::note()
+        };
+
+        let mut enc = NoteEncoder::begin(&[0x01; 32], &[0x02; 32], &[0x03; 64], 0, 0);
+        for i in 0..300 {
+            enc.begin_tag()
+                .elem_str("t")
+                .elem_bytes(&[])
+                .elem(&i.to_string());
+        }
+        let packed = enc.finish();
+
+        assert_eq!(packed, pack_note(&note).expect("pack"));
+        NoteParser::new(&packed)
+            .strict()
+            .into_note()
+            .expect("canonical");
+    }
+
+    #[test]
+    #[should_panic(expected = "begin_tag")]
+    fn elements_need_a_tag() {
+        NoteEncoder::begin(&[0; 32], &[0; 32], &[0; 64], 0, 0).elem_str("p");
+    }
+}
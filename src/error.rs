@@ -8,6 +8,42 @@ pub enum Error {
     Decode(base64::DecodeError),
     InvalidPrefix,
     Json(serde_json::Error),
+    Io(std::io::Error),
+    InvalidStreamHeader,
+    InvalidArchive,
+    NoteTooLarge,
+    ContentTooLarge,
+    TooManyTags,
+    TooManyTagElems,
+    TagElemTooLarge,
+    InvalidId,
+    InvalidPubkey,
+    InvalidSig,
+    UnsupportedVersion(u64),
+    NonCanonicalVarint,
+    TrailingBytes,
+    EmptyBytes,
+    /// A note that [`pack_note_checked`](crate::pack_note_checked) would not
+    /// unpack to the same canonical serialization.
+    RoundTrip {
+        /// Index of the first tag that differs, or the number of tags if
+        /// they all match.
+        tag: usize,
+        /// Index of the first element in that tag that differs.
+        elem: usize,
+    },
+    BufferTooSmall,
+    /// A decode error annotated with where in the payload it happened.
+    At {
+        /// Byte offset from the start of the payload where `field` begins.
+        offset: usize,
+        /// The field being parsed.
+        field: DecodeField,
+        /// The underlying error.
+        error: Box<Error>,
+    },
+    #[cfg(feature = "secp256k1")]
+    Secp256k1(secp256k1::Error),
 }
 
 impl core::fmt::Display for Error {
@@ -37,6 +73,71 @@ impl core::fmt::Display for Error {
             Error::Json(err) => {
                 write!(f, "json error: {err}")
             }
+            Error::Io(err) => {
+                write!(f, "io error: {err}")
+            }
+            Error::InvalidStreamHeader => {
+                write!(f, "stream did not start with a notepack stream header")
+            }
+            Error::InvalidArchive => {
+                write!(f, "malformed notepack archive")
+            }
+            Error::NoteTooLarge => {
+                write!(f, "note exceeds the maximum total size")
+            }
+            Error::ContentTooLarge => {
+                write!(f, "content exceeds the maximum length")
+            }
+            Error::TooManyTags => {
+                write!(f, "note exceeds the maximum number of tags")
+            }
+            Error::TooManyTagElems => {
+                write!(f, "tag exceeds the maximum number of elements")
+            }
+            Error::TagElemTooLarge => {
+                write!(f, "tag element exceeds the maximum length")
+            }
+            Error::InvalidId => {
+                write!(f, "id must be 32 bytes of lowercase hex")
+            }
+            Error::InvalidPubkey => {
+                write!(f, "pubkey must be 32 bytes of lowercase hex")
+            }
+            Error::InvalidSig => {
+                write!(f, "sig must be 64 bytes of lowercase hex")
+            }
+            Error::UnsupportedVersion(v) => {
+                write!(f, "unsupported notepack version {v}")
+            }
+            Error::NonCanonicalVarint => {
+                write!(f, "varint is not minimally encoded")
+            }
+            Error::TrailingBytes => {
+                write!(f, "unexpected bytes after the last tag")
+            }
+            Error::EmptyBytes => {
+                write!(f, "empty bytes tag element")
+            }
+            Error::RoundTrip { tag, elem } => {
+                write!(
+                    f,
+                    "tag {tag} element {elem} does not round-trip through notepack"
+                )
+            }
+            Error::BufferTooSmall => {
+                write!(f, "output buffer is too small for the encoded note")
+            }
+            Error::At {
+                offset,
+                field,
+                error,
+            } => {
+                write!(f, "{field} at offset {offset}: {error}")
+            }
+            #[cfg(feature = "secp256k1")]
+            Error::Secp256k1(err) => {
+                write!(f, "secp256k1 error: {err}")
+            }
         }
     }
 }
@@ -65,4 +166,108 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl From<secp256k1::Error> for Error {
+    fn from(err: secp256k1::Error) -> Self {
+        Error::Secp256k1(err)
+    }
+}
+
+// Display already includes any wrapped error, so `source` stays `None` to
+// keep error reporters from printing the inner message twice.
 impl std::error::Error for Error {}
+
+impl Error {
+    /// The underlying error, with any [`Error::At`] context stripped.
+    ///
+    /// Handy for matching on what went wrong regardless of where:
+    ///
+    /// ```rust
+    /// # use notepack::{Error, NoteParser};
+    /// let err = NoteParser::new(&[1, 0xaa]).into_note().unwrap_err();
+    /// assert!(matches!(err.root(), Error::Truncated));
+    /// assert_eq!(err.offset(), Some(1));
+    /// assert_eq!(err.to_string(), "id at offset 1: notepack string is truncated");
+    /// // the message already names the cause, so there is no separate source
+    /// assert!(std::error::Error::source(&err).is_none());
+    /// ```
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::At { error, .. } => error.root(),
+            other => other,
+        }
+    }
+
+    /// Byte offset of the field that failed to decode, if known.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::At { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// The field that failed to decode, if known.
+    pub fn field(&self) -> Option<DecodeField> {
+        match self {
+            Error::At { field, .. } => Some(*field),
+            _ => None,
+        }
+    }
+
+    /// Annotate this error with the field and offset it occurred at.
+    pub(crate) fn at(self, offset: usize, field: DecodeField) -> Error {
+        Error::At {
+            offset,
+            field,
+            error: Box::new(self),
+        }
+    }
+}
+
+/// Identifies which part of a notepack payload was being decoded.
+///
+/// Tag and element indices are zero-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeField {
+    Version,
+    Id,
+    Pubkey,
+    Sig,
+    CreatedAt,
+    Kind,
+    Content,
+    NumTags,
+    NumTagElems {
+        tag: u64,
+    },
+    TagElem {
+        tag: u64,
+        elem: u64,
+    },
+    /// The end of the note, after the last tag.
+    End,
+}
+
+impl core::fmt::Display for DecodeField {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeField::Version => write!(f, "version"),
+            DecodeField::Id => write!(f, "id"),
+            DecodeField::Pubkey => write!(f, "pubkey"),
+            DecodeField::Sig => write!(f, "sig"),
+            DecodeField::CreatedAt => write!(f, "created_at"),
+            DecodeField::Kind => write!(f, "kind"),
+            DecodeField::Content => write!(f, "content"),
+            DecodeField::NumTags => write!(f, "tag count"),
+            DecodeField::NumTagElems { tag } => write!(f, "tag {tag} element count"),
+            DecodeField::TagElem { tag, elem } => write!(f, "tag {tag} element {elem}"),
+            DecodeField::End => write!(f, "end of note"),
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{Error, Note, StringType};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A NIP-01 subscription filter that matches directly against packed notes.
+///
+/// Ids and authors are kept as raw bytes and compared against [`Note::id`] /
+/// [`Note::pubkey`] without hex-encoding. Tag filters (`#e`, `#p`, `#t`, …)
+/// walk the lazy [`Tags`](crate::Tags) cursor and compare hex filter values
+/// against [`StringType::Bytes`] elements nibble by nibble.
+///
+/// Every condition that is set must match; within a condition any listed value
+/// may match. `limit` is carried along for callers running queries but is not
+/// used by [`Filter::matches`].
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{Filter, NoteBuf, NoteParser, pack_note};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     kind: 1,
+///     tags: vec![vec!["p".into(), "dd".repeat(32)]],
+///     ..NoteBuf::default()
+/// };
+/// let packed = pack_note(&note).unwrap();
+/// let note = NoteParser::new(&packed).into_note().unwrap();
+///
+/// let filter: Filter = serde_json::from_str(&format!(
+///     r##"{{"kinds":[1],"#p":["{}"]}}"##,
+///     "dd".repeat(32)
+/// ))
+/// .unwrap();
+/// assert!(filter.matches(&note).unwrap());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawFilter")]
+pub struct Filter {
+    /// Event ids to match.
+    pub ids: Option<Vec<[u8; 32]>>,
+    /// Author pubkeys to match.
+    pub authors: Option<Vec<[u8; 32]>>,
+    /// Event kinds to match.
+    pub kinds: Option<Vec<u64>>,
+    /// Single-letter tag filters, keyed by tag name (`'e'` for `#e`).
+    pub tags: BTreeMap<char, Vec<String>>,
+    /// Only match notes with `created_at >= since`.
+    pub since: Option<u64>,
+    /// Only match notes with `created_at <= until`.
+    pub until: Option<u64>,
+    /// Maximum number of notes a query should return.
+    pub limit: Option<u64>,
+}
+
+impl Filter {
+    /// Check whether `note` satisfies this filter.
+    ///
+    /// Fixed-size fields are checked first, so tags are only walked when
+    /// everything else already matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if the tags block has to be read and is malformed.
+    pub fn matches(&self, note: &Note<'_>) -> Result<bool, Error> {
+        if let Some(ids) = &self.ids
+            && !ids.iter().any(|id| id == note.id)
+        {
+            return Ok(false);
+        }
+
+        if let Some(authors) = &self.authors
+            && !authors.iter().any(|pk| pk == note.pubkey)
+        {
+            return Ok(false);
+        }
+
+        if let Some(kinds) = &self.kinds
+            && !kinds.contains(&note.kind)
+        {
+            return Ok(false);
+        }
+
+        if self.since.is_some_and(|since| note.created_at < since)
+            || self.until.is_some_and(|until| note.created_at > until)
+        {
+            return Ok(false);
+        }
+
+        if self.tags.is_empty() {
+            return Ok(true);
+        }
+
+        self.matches_tags(note)
+    }
+
+    fn matches_tags(&self, note: &Note<'_>) -> Result<bool, Error> {
+        let mut unmatched = self.tags.len();
+        let mut satisfied: BTreeMap<char, bool> =
+            self.tags.keys().map(|&letter| (letter, false)).collect();
+
+        let mut tags = note.tags.clone();
+        while let Some(mut elems) = tags.next_tag()? {
+            let letter = match elems.next().transpose()? {
+                Some(StringType::Str(name)) => match single_char(name) {
+                    Some(letter) => letter,
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            let (Some(values), Some(done)) = (self.tags.get(&letter), satisfied.get_mut(&letter))
+            else {
+                continue;
+            };
+            if *done {
+                continue;
+            }
+
+            let Some(value) = elems.next().transpose()? else {
+                continue;
+            };
+            if values.iter().any(|v| elem_eq(v, &value)) {
+                *done = true;
+                unmatched -= 1;
+                if unmatched == 0 {
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Compare a filter value against a tag element without allocating.
+fn elem_eq(value: &str, elem: &StringType<'_>) -> bool {
+    match elem {
+        StringType::Str(s) => value == *s,
+        StringType::Bytes(bs) => hex_eq(value, bs),
+    }
+}
+
+/// Whether `hex` is the lowercase hex encoding of `bytes`.
+pub(crate) fn hex_eq(hex: &str, bytes: &[u8]) -> bool {
+    let hex = hex.as_bytes();
+    if hex.len() != bytes.len() * 2 {
+        return false;
+    }
+
+    hex.chunks_exact(2).zip(bytes).all(|(pair, &b)| {
+        matches!(
+            (nibble(pair[0]), nibble(pair[1])),
+            (Some(hi), Some(lo)) if (hi << 4 | lo) == b
+        )
+    })
+}
+
+fn nibble(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        _ => None,
+    }
+}
+
+/// The JSON shape of a NIP-01 filter, before hex ids are decoded.
+#[derive(Deserialize)]
+struct RawFilter {
+    ids: Option<Vec<String>>,
+    authors: Option<Vec<String>>,
+    kinds: Option<Vec<u64>>,
+    since: Option<u64>,
+    until: Option<u64>,
+    limit: Option<u64>,
+    #[serde(flatten)]
+    rest: BTreeMap<String, serde_json::Value>,
+}
+
+impl TryFrom<RawFilter> for Filter {
+    type Error = String;
+
+    fn try_from(raw: RawFilter) -> Result<Self, Self::Error> {
+        let decode_all = |field: &str, hexes: Option<Vec<String>>| {
+            hexes
+                .map(|hexes| {
+                    hexes
+                        .iter()
+                        .map(|h| {
+                            let mut out = [0u8; 32];
+                            hex::decode_to_slice(h, &mut out)
+                                .map_err(|e| format!("invalid {field} entry {h:?}: {e}"))?;
+                            Ok(out)
+                        })
+                        .collect::<Result<Vec<_>, String>>()
+                })
+                .transpose()
+        };
+
+        let mut tags = BTreeMap::new();
+        for (key, value) in raw.rest {
+            let Some(letter) = key.strip_prefix('#').and_then(single_char) else {
+                continue;
+            };
+            let values: Vec<String> = serde_json::from_value(value)
+                .map_err(|e| format!("invalid tag filter {key:?}: {e}"))?;
+            tags.insert(letter, values);
+        }
+
+        Ok(Filter {
+            ids: decode_all("ids", raw.ids)?,
+            authors: decode_all("authors", raw.authors)?,
+            kinds: raw.kinds,
+            tags,
+            since: raw.since,
+            until: raw.until,
+            limit: raw.limit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoteBuf, NoteParser, pack_note, test_util};
+
+    fn packed() -> Vec<u8> {
+        let note = NoteBuf {
+            created_at: 1_000,
+            kind: 1,
+            tags: vec![
+                vec!["e".into(), "aa".repeat(32), "wss://relay".into()],
+                vec!["p".into(), "bb".repeat(32)],
+                vec!["t".into(), "nostr".into()],
+                vec!["t".into(), "cafe".into()],
+                vec!["title".into(), "ignored".into()],
+            ],
+            content: "hi".into(),
+            ..test_util::note()
+        };
+        pack_note(&note).expect("pack")
+    }
+
+    fn check(json: &str) -> bool {
+        let bytes = packed();
+        let note = NoteParser::new(&bytes).into_note().expect("parse");
+        let filter: Filter = serde_json::from_str(json).expect("filter json");
+        filter.matches(&note).expect("matches")
+    }
+
+    #[test]
+    fn matches_fixed_fields() {
+        assert!(check("{}"));
+        assert!(check(&format!(r#"{{"ids":["{}"]}}"#, "01".repeat(32))));
+        assert!(!check(&format!(r#"{{"ids":["{}"]}}"#, "ff".repeat(32))));
+        assert!(check(&format!(r#"{{"authors":["{}"]}}"#, "02".repeat(32))));
+        assert!(!check(&format!(r#"{{"authors":["{}"]}}"#, "01".repeat(32))));
+        assert!(check(r#"{"kinds":[0,1]}"#));
+        assert!(!check(r#"{"kinds":[3]}"#));
+        assert!(!check(r#"{"ids":[]}"#));
+    }
+
+    #[test]
+    fn matches_time_range() {
+        assert!(check(r#"{"since":1000,"until":1000}"#));
+        assert!(!check(r#"{"since":1001}"#));
+        assert!(!check(r#"{"until":999}"#));
+    }
+
+    #[test]
+    fn matches_tag_filters_against_bytes_and_text() {
+        assert!(check(&format!(r##"{{"#e":["{}"]}}"##, "aa".repeat(32))));
+        assert!(check(&format!(
+            r##"{{"#e":["{}"],"#p":["{}"]}}"##,
+            "aa".repeat(32),
+            "bb".repeat(32)
+        )));
+        assert!(!check(&format!(r##"{{"#p":["{}"]}}"##, "aa".repeat(32))));
+        // uppercase hex never matches the lowercase-only Bytes encoding
+        assert!(!check(&format!(r##"{{"#e":["{}"]}}"##, "AA".repeat(32))));
+        assert!(check(r##"{"#t":["nostr"]}"##));
+        assert!(check(r##"{"#t":["cafe"]}"##));
+        assert!(!check(r##"{"#t":["bitcoin"]}"##));
+        assert!(!check(r##"{"#x":["anything"]}"##));
+    }
+
+    #[test]
+    fn deserialize_rejects_bad_hex_and_ignores_unknown_keys() {
+        let filter: Filter =
+            serde_json::from_str(r##"{"limit":10,"search":"x","#title":["a"]}"##).expect("ok");
+        assert_eq!(filter.limit, Some(10));
+        assert!(filter.tags.is_empty());
+
+        assert!(serde_json::from_str::<Filter>(r#"{"ids":["abcd"]}"#).is_err());
+    }
+}
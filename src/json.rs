@@ -0,0 +1,578 @@
+use crate::canonical::{write_hex, write_tags_json, write_u64};
+use crate::parser::NOTEPACK_VERSION;
+use crate::{EncodeOptions, Error, Note, decode_fixed_hex, put_elem, put_varint};
+use serde::Deserialize;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::io::Write;
+
+/// Transcode a NIP-01 JSON event straight to its notepack payload.
+///
+/// This produces the same bytes as deserializing a [`NoteBuf`](crate::NoteBuf)
+/// and calling [`pack_note`](crate::pack_note), but tag elements and content
+/// are written to the output as they are tokenized, so there is no `String`
+/// per element. Unknown fields are ignored, as with `NoteBuf`.
+///
+/// # Errors
+///
+/// Returns [`Error::Json`] for malformed JSON or missing fields, and the same
+/// [`Error`]s as [`pack_note`](crate::pack_note) for a malformed id, pubkey
+/// or sig.
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, json_to_notepack, pack_note};
+///
+/// let json = format!(
+///     r#"{{"id":"{}","pubkey":"{}","created_at":1,"kind":1,"tags":[["t","nostr"]],"content":"hi","sig":"{}"}}"#,
+///     "aa".repeat(32),
+///     "bb".repeat(32),
+///     "cc".repeat(64),
+/// );
+///
+/// let note: NoteBuf = serde_json::from_str(&json).unwrap();
+/// assert_eq!(json_to_notepack(&json).unwrap(), pack_note(&note).unwrap());
+/// ```
+pub fn json_to_notepack(json: &str) -> Result<Vec<u8>, Error> {
+    json_to_notepack_with(json, &EncodeOptions::DEFAULT)
+}
+
+/// Like [`json_to_notepack`], but with [`EncodeOptions`] controlling how tag
+/// elements are stored, matching [`pack_note_with`](crate::pack_note_with).
+///
+/// # Errors
+///
+/// Returns the same [`Error`]s as [`json_to_notepack`].
+pub fn json_to_notepack_with(json: &str, options: &EncodeOptions) -> Result<Vec<u8>, Error> {
+    let mut de = serde_json::Deserializer::from_str(json);
+    let packed = NoteSeed(options).deserialize(&mut de)?;
+    de.end()?;
+    packed
+}
+
+/// Write `note` as NIP-01 JSON to `writer`.
+///
+/// The output is the same as `serde_json::to_writer(writer, note)`, but the
+/// tags are walked lazily and written as they are read, with
+/// [`StringType::Bytes`](crate::StringType::Bytes) elements hex-encoded on the
+/// fly, so nothing is materialized. Output is written in many small pieces;
+/// wrap unbuffered writers such as sockets or files in a
+/// [`std::io::BufWriter`].
+///
+/// # Errors
+///
+/// Returns [`Error::Io`] if writing fails, or the decode [`Error`] from a
+/// malformed tags block. Either way `writer` may have received part of the
+/// JSON.
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, NoteParser, pack_note, write_note_json};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     tags: vec![vec!["p".into(), "dd".repeat(32)]],
+///     content: "hi\n".into(),
+///     ..NoteBuf::default()
+/// };
+/// let packed = pack_note(&note).unwrap();
+/// let parsed = NoteParser::new(&packed).into_note().unwrap();
+///
+/// let mut json = Vec::new();
+/// write_note_json(&parsed, &mut json).unwrap();
+/// assert_eq!(json, serde_json::to_vec(&note).unwrap());
+/// ```
+pub fn write_note_json(note: &Note<'_>, mut writer: impl Write) -> Result<(), Error> {
+    // the sink can't return errors, so keep the first one and stop writing
+    let mut io_result = Ok(());
+    let written = write_json(
+        &mut |bs| {
+            if io_result.is_ok() {
+                io_result = writer.write_all(bs);
+            }
+        },
+        note,
+    );
+    io_result?;
+    written
+}
+
+/// Write `note` as a NIP-01 JSON object, in the field order of `impl Serialize for Note`.
+fn write_json(out: &mut impl FnMut(&[u8]), note: &Note<'_>) -> Result<(), Error> {
+    out(b"{\"id\":\"");
+    write_hex(out, note.id);
+    out(b"\",\"pubkey\":\"");
+    write_hex(out, note.pubkey);
+    out(b"\",\"created_at\":");
+    write_u64(out, note.created_at);
+    out(b",\"kind\":");
+    write_u64(out, note.kind);
+
+    out(b",\"tags\":");
+    write_tags_json(out, &note.tags, write_json_string)?;
+    out(b",\"content\":");
+    write_json_string(out, note.content);
+    out(b",\"sig\":\"");
+    write_hex(out, note.sig);
+    out(b"\"}");
+
+    Ok(())
+}
+
+/// Write `s` as a JSON string literal, escaped the way `serde_json` does.
+///
+/// Unlike the NIP-01 id serialization in `canonical.rs`, every control
+/// character is escaped, so the output is always valid JSON.
+fn write_json_string(out: &mut impl FnMut(&[u8]), s: &str) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    out(b"\"");
+
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        let unicode;
+        let escaped: &[u8] = match b {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0x08 => b"\\b",
+            0x0c => b"\\f",
+            0x00..=0x1f => {
+                unicode = [
+                    b'\\',
+                    b'u',
+                    b'0',
+                    b'0',
+                    HEX[(b >> 4) as usize],
+                    HEX[(b & 0xf) as usize],
+                ];
+                &unicode
+            }
+            _ => continue,
+        };
+        if start < i {
+            out(&bytes[start..i]);
+        }
+        out(escaped);
+        start = i + 1;
+    }
+    if start < bytes.len() {
+        out(&bytes[start..]);
+    }
+
+    out(b"\"");
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum Field {
+    Id,
+    Pubkey,
+    CreatedAt,
+    Kind,
+    Tags,
+    Content,
+    Sig,
+    #[serde(other)]
+    Other,
+}
+
+/// The whole event. Its value is itself a `Result`, so a malformed id,
+/// pubkey or sig surfaces as the matching [`Error`] rather than a JSON error.
+struct NoteSeed<'o>(&'o EncodeOptions);
+
+impl<'de> DeserializeSeed<'de> for NoteSeed<'_> {
+    type Value = Result<Vec<u8>, Error>;
+
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<Self::Value, D::Error> {
+        de.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NoteSeed<'_> {
+    type Value = Result<Vec<u8>, Error>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a nostr event")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut id = None;
+        let mut pubkey = None;
+        let mut sig = None;
+        let mut created_at = None;
+        let mut kind = None;
+        let mut content = None;
+        let mut tags = None;
+
+        while let Some(field) = map.next_key()? {
+            match field {
+                Field::Id => set(&mut id, "id", map.next_value_seed(FixedHex::<32>)?)?,
+                Field::Pubkey => set(&mut pubkey, "pubkey", map.next_value_seed(FixedHex::<32>)?)?,
+                Field::Sig => set(&mut sig, "sig", map.next_value_seed(FixedHex::<64>)?)?,
+                Field::CreatedAt => set(&mut created_at, "created_at", map.next_value()?)?,
+                Field::Kind => set(&mut kind, "kind", map.next_value()?)?,
+                Field::Content => {
+                    let mut buf = Vec::new();
+                    map.next_value_seed(ContentSeed(&mut buf))?;
+                    set(&mut content, "content", buf)?;
+                }
+                Field::Tags => {
+                    let mut buf = Vec::new();
+                    let num_tags = map.next_value_seed(TagsSeed(&mut buf, self.0))?;
+                    set(&mut tags, "tags", (num_tags, buf))?;
+                }
+                Field::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
+        let pubkey = pubkey.ok_or_else(|| de::Error::missing_field("pubkey"))?;
+        let sig = sig.ok_or_else(|| de::Error::missing_field("sig"))?;
+        let created_at = created_at.ok_or_else(|| de::Error::missing_field("created_at"))?;
+        let kind = kind.ok_or_else(|| de::Error::missing_field("kind"))?;
+        let content = content.ok_or_else(|| de::Error::missing_field("content"))?;
+        let (num_tags, tags) = tags.ok_or_else(|| de::Error::missing_field("tags"))?;
+
+        // checked in the same order as pack_note
+        let (id, pubkey, sig) = match (
+            id.ok_or(Error::InvalidId),
+            pubkey.ok_or(Error::InvalidPubkey),
+            sig.ok_or(Error::InvalidSig),
+        ) {
+            (Ok(id), Ok(pubkey), Ok(sig)) => (id, pubkey, sig),
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return Ok(Err(e)),
+        };
+
+        // the version, fixed-size fields and four varints take at most 159 bytes
+        let mut buf = Vec::with_capacity(159 + content.len() + tags.len());
+        let mut out = |bs: &[u8]| buf.extend_from_slice(bs);
+        put_varint(&mut out, NOTEPACK_VERSION as u64);
+        out(&id);
+        out(&pubkey);
+        out(&sig);
+        put_varint(&mut out, created_at);
+        put_varint(&mut out, kind);
+        out(&content);
+        put_varint(&mut out, num_tags);
+        out(&tags);
+        Ok(Ok(buf))
+    }
+}
+
+fn set<T, E: de::Error>(slot: &mut Option<T>, name: &'static str, value: T) -> Result<(), E> {
+    if slot.replace(value).is_some() {
+        return Err(E::duplicate_field(name));
+    }
+    Ok(())
+}
+
+/// An id, pubkey or sig: `N` bytes of lowercase hex, or `None` if malformed.
+struct FixedHex<const N: usize>;
+
+impl<'de, const N: usize> DeserializeSeed<'de> for FixedHex<N> {
+    type Value = Option<[u8; N]>;
+
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<Self::Value, D::Error> {
+        de.deserialize_str(self)
+    }
+}
+
+impl<const N: usize> Visitor<'_> for FixedHex<N> {
+    type Value = Option<[u8; N]>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{N} bytes of hex")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        // the error is a placeholder; the caller knows which field this is
+        Ok(decode_fixed_hex(s, Error::InvalidId).ok())
+    }
+}
+
+/// The content, written to the buffer with its length prefix.
+struct ContentSeed<'b>(&'b mut Vec<u8>);
+
+impl<'de> DeserializeSeed<'de> for ContentSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<(), D::Error> {
+        de.deserialize_str(self)
+    }
+}
+
+impl Visitor<'_> for ContentSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<(), E> {
+        put_varint(&mut |bs| self.0.extend_from_slice(bs), s.len() as u64);
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// The tags array, written to the buffer tag by tag. Its value is the tag count.
+struct TagsSeed<'b, 'o>(&'b mut Vec<u8>, &'o EncodeOptions);
+
+impl<'de> DeserializeSeed<'de> for TagsSeed<'_, '_> {
+    type Value = u64;
+
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<u64, D::Error> {
+        de.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TagsSeed<'_, '_> {
+    type Value = u64;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of tags")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<u64, A::Error> {
+        // element counts are only known at the end of each tag, so elements
+        // go to a scratch buffer that is reused across tags
+        let mut elems = Vec::new();
+        let mut name = String::new();
+        let mut num_tags = 0;
+        while let Some(num_elems) = seq.next_element_seed(TagSeed {
+            out: &mut elems,
+            name: &mut name,
+            options: self.1,
+        })? {
+            put_varint(&mut |bs| self.0.extend_from_slice(bs), num_elems);
+            self.0.append(&mut elems);
+            num_tags += 1;
+        }
+        Ok(num_tags)
+    }
+}
+
+/// One tag, written to the buffer without its element count. Its value is
+/// the element count.
+struct TagSeed<'b, 'o> {
+    out: &'b mut Vec<u8>,
+    name: &'b mut String, // element 0, reused across tags
+    options: &'o EncodeOptions,
+}
+
+impl<'de> DeserializeSeed<'de> for TagSeed<'_, '_> {
+    type Value = u64;
+
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<u64, D::Error> {
+        de.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TagSeed<'_, '_> {
+    type Value = u64;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of strings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<u64, A::Error> {
+        self.name.clear();
+        let mut num_elems = 0;
+        while seq
+            .next_element_seed(ElemSeed {
+                out: &mut *self.out,
+                name: &mut *self.name,
+                index: num_elems as usize,
+                options: self.options,
+            })?
+            .is_some()
+        {
+            num_elems += 1;
+        }
+        Ok(num_elems)
+    }
+}
+
+/// One tag element, stored as bytes or text as [`pack_note_with`](crate::pack_note_with) would.
+struct ElemSeed<'b, 'o> {
+    out: &'b mut Vec<u8>,
+    name: &'b mut String,
+    index: usize,
+    options: &'o EncodeOptions,
+}
+
+impl<'de> DeserializeSeed<'de> for ElemSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<(), D::Error> {
+        de.deserialize_str(self)
+    }
+}
+
+impl Visitor<'_> for ElemSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<(), E> {
+        if self.index == 0 {
+            self.name.push_str(s);
+        }
+        let as_bytes = self.options.stores_as_bytes(self.name, self.index, s);
+        put_elem(&mut |bs| self.out.extend_from_slice(bs), s, as_bytes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoteBuf, NoteParser, pack_note, pack_note_with, test_util};
+
+    fn event(id: &str, tags: &str) -> String {
+        format!(
+            r#"{{"id":"{id}","pubkey":"{}","created_at":1720000000,"kind":30023,"tags":{tags},"content":"café \"quoted\"\n","sig":"{}","extra":[1,{{"a":null}}]}}"#,
+            "02".repeat(32),
+            "03".repeat(64),
+        )
+    }
+
+    #[test]
+    fn matches_serde_and_pack_note() {
+        let tags = format!(
+            r#"[["e","{}","wss://relay"],[],["t","","cafe","ABCD","escaped \\ \/"],["x","{}"]]"#,
+            "aa".repeat(32),
+            "0f".repeat(100),
+        );
+        let json = event(&"01".repeat(32), &tags);
+
+        let note: NoteBuf = serde_json::from_str(&json).expect("serde");
+        assert_eq!(
+            json_to_notepack(&json).expect("transcode"),
+            pack_note(&note).expect("pack")
+        );
+        for options in [
+            EncodeOptions::NO_HEX_COMPRESSION,
+            EncodeOptions::references_only(),
+            EncodeOptions {
+                min_hex_bytes: 32,
+                ..EncodeOptions::DEFAULT
+            },
+        ] {
+            assert_eq!(
+                json_to_notepack_with(&json, &options).expect("transcode"),
+                pack_note_with(&note, &options).expect("pack"),
+                "{options:?}"
+            );
+        }
+
+        // field order doesn't matter
+        let json = r#"{"tags":[["p","bb"]],"content":"","sig":"SIG","kind":1,"created_at":2,"pubkey":"PK","id":"ID"}"#
+            .replace("SIG", &"03".repeat(64))
+            .replace("PK", &"02".repeat(32))
+            .replace("ID", &"01".repeat(32));
+        let note: NoteBuf = serde_json::from_str(&json).expect("serde");
+        assert_eq!(
+            json_to_notepack(&json).expect("transcode"),
+            pack_note(&note).expect("pack")
+        );
+    }
+
+    #[test]
+    fn rejects_what_pack_note_rejects() {
+        let bad_id = event(&"AB".repeat(32), "[]");
+        assert!(matches!(json_to_notepack(&bad_id), Err(Error::InvalidId)));
+
+        let short_pubkey = event(&"01".repeat(32), "[]").replace(&"02".repeat(32), "02");
+        assert!(matches!(
+            json_to_notepack(&short_pubkey),
+            Err(Error::InvalidPubkey)
+        ));
+
+        for json in [
+            event(&"01".repeat(32), r#"[["t",1]]"#),
+            event(&"01".repeat(32), "[]").replace(r#""kind":30023,"#, ""),
+            event(&"01".repeat(32), "[]").replace(r#""kind":30023"#, r#""kind":1,"kind":2"#),
+            event(&"01".repeat(32), "[]") + " x",
+        ] {
+            let err = json_to_notepack(&json).unwrap_err();
+            assert!(matches!(err, Error::Json(_)), "{json}: {err}");
+            assert!(serde_json::from_str::<NoteBuf>(&json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn write_note_json_matches_serde() {
+        let note = NoteBuf {
+            created_at: u64::MAX,
+            kind: 30_023,
+            tags: vec![
+                vec!["e".into(), "aa".repeat(32), "wss://relay".into()],
+                vec![],
+                vec![
+                    "t".into(),
+                    "".into(),
+                    "ABCD".into(),
+                    "a\u{1}\u{1f}\u{7f}/".into(),
+                ],
+            ],
+            content: "line\nquote\" back\\ tab\t\u{8}\u{c}\r\u{0} é 🎉".into(),
+            ..test_util::note()
+        };
+        let packed = pack_note(&note).expect("pack");
+        let parsed = NoteParser::new(&packed).into_note().expect("parse");
+
+        let mut json = Vec::new();
+        write_note_json(&parsed, &mut json).expect("write");
+        assert_eq!(
+            String::from_utf8(json).expect("utf8"),
+            serde_json::to_string(&parsed).expect("serde")
+        );
+
+        // only the remaining tags are written
+        let mut rest = parsed.clone();
+        rest.tags.next_tag().expect("tag").expect("first");
+        let mut json = Vec::new();
+        write_note_json(&rest, &mut json).expect("write");
+        assert_eq!(
+            String::from_utf8(json).expect("utf8"),
+            serde_json::to_string(&rest).expect("serde")
+        );
+        assert!(
+            serde_json::to_string(&rest)
+                .expect("serde")
+                .contains(r#""tags":[[],["#)
+        );
+    }
+
+    #[test]
+    fn write_note_json_reports_errors() {
+        let note = NoteBuf {
+            tags: vec![vec!["t".into(), "nostr".into()]],
+            ..test_util::note()
+        };
+        let packed = pack_note(&note).expect("pack");
+
+        let truncated = NoteParser::new(&packed[..packed.len() - 1])
+            .into_note()
+            .expect("parse");
+        let err = write_note_json(&truncated, Vec::new()).unwrap_err();
+        assert!(matches!(err.root(), Error::Truncated));
+
+        let parsed = NoteParser::new(&packed).into_note().expect("parse");
+        let mut small = [0u8; 16];
+        let err = write_note_json(&parsed, &mut small[..]).unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+    }
+}
@@ -14,6 +14,14 @@
 //!
 //! - **Compact binary format** using varint encoding for integers.
 //! - **Streaming parser**: no allocation-heavy parsing; fields are yielded one by one as they’re read.
+//! - **Event id checks**: [`Note::compute_id`] / [`Note::verify_id`] hash the NIP-01 serialization
+//!   straight from the packed note.
+//!
+//! ## Cargo features
+//!
+//! - `secp256k1` — enables `Note::verify_signature` for BIP-340 Schnorr signature checks, and
+//!   `NoteBuilder` for authoring and signing new notes.
+//! - `mmap` — enables `MappedFile` for zero-copy reads of archives and framed streams via mmap.
 //!
 //! ## Example: Encoding a Note
 //!
@@ -40,7 +48,7 @@
 //! ```rust
 //! use notepack::{NoteParser, ParsedField};
 //!
-//! let b64 = "notepack_737yskaxtaKQSL3IPPhOOR8T1R4G/f4ARPHGeNPfOpF4417q9YtU+4JZGOD3+Y0S3uVU6/edo64oTqJQ0pOF29Ms7GmX6fzM4Wjc6sohGPlbdRGLjhuqIRccETX5DliwUFy9qGg2lDD9oMl8ijoNFq4wwJ5Ikmr4Vh7NYWBwOkuo/anEBgECaGkA";
+//! let b64 = "notepack_AfHnvCqXVkU/zA6A7PYhg/qVuaEnigEoHbwxC2d3Mg6Af+Q321iE7gE/cBp1+NGoTstDTpl/KjFBFoVVH//xuEF1UH+E14IRpo8vlkIh9Vh6qVembBlB0BElyqB7mqvfWpjD5j0f4eMHy/AbdLChuV/+Y262dGwAFn4NSOWxEDLVlsmpxAYBAmhpAA";
 //! let bytes = NoteParser::decode(b64).unwrap();
 //! let parser = NoteParser::new(&bytes);
 //!
@@ -79,18 +87,52 @@
 //! Nostr notes. Strings that look like 32-byte hex are stored more compactly; integers are encoded
 //! as LEB128-style varints; and the format starts with a `version` field for forward compatibility.
 
+mod archive;
+mod binary;
+#[cfg(feature = "secp256k1")]
+mod builder;
+mod canonical;
+mod encoder;
 mod error;
+mod filter;
+mod json;
+mod limits;
+#[cfg(feature = "mmap")]
+mod mmap;
 mod note;
+mod options;
+mod owned;
 mod parser;
+mod push;
+mod stream;
 mod stringtype;
+#[cfg(test)]
+mod test_util;
 mod varint;
 
-pub use error::Error;
-pub use note::{Note, NoteBuf, Tags, TagElems};
-pub use parser::{NoteParser, ParsedField, ParserState};
-pub use stringtype::StringType;
+pub use archive::{ARCHIVE_MAGIC, ARCHIVE_VERSION, Archive, ArchiveWriter};
+pub use binary::BinaryNote;
+#[cfg(feature = "secp256k1")]
+pub use builder::NoteBuilder;
+pub use encoder::NoteEncoder;
+pub use error::{DecodeField, Error};
+pub use filter::Filter;
+pub use json::{json_to_notepack, json_to_notepack_with, write_note_json};
+pub use limits::DecodeLimits;
+#[cfg(feature = "mmap")]
+pub use mmap::MappedFile;
+pub use note::{Note, NoteBuf, TagElems, Tags};
+pub use options::EncodeOptions;
+pub use owned::OwnedNote;
+pub use parser::{NOTEPACK_VERSION, NoteParser, ParsedField, ParserState};
+pub use push::PushParser;
+pub use stream::{NoteReader, NoteStream, NoteWriter, STREAM_MAGIC, STREAM_VERSION};
+pub use stringtype::{StringType, StringTypeBuf};
+
+#[cfg(feature = "secp256k1")]
+pub use secp256k1;
 
-use varint::{write_tagged_varint, write_varint};
+use varint::{encode_varint, varint_len};
 
 /// Packs a [`Note`] into its compact binary notepack representation.
 ///
@@ -103,56 +145,351 @@ use varint::{write_tagged_varint, write_varint};
 /// Returns a `Vec<u8>` containing the binary payload, or an [`Error`] if hex decoding fails.
 ///
 /// This is the low-level encoding API—most callers will want [`pack_note_to_string`] instead.
+/// Bulk converters can reuse one buffer with [`pack_note_into`], and producers
+/// that generate tags on the fly can use [`NoteEncoder`].
 ///
 /// # Errors
 ///
-/// Returns [`Error::Hex`] if any hex string field (like `id`, `pubkey`, or `sig`) fails to decode.
+/// Returns [`Error::InvalidId`], [`Error::InvalidPubkey`] or [`Error::InvalidSig`]
+/// if `id`, `pubkey` or `sig` is not exactly 32, 32 or 64 bytes of lowercase
+/// hex (SPEC §4.1). Anything else would be misparsed as shifted fields.
 ///
 /// # Example
 ///
 /// ```rust
-/// use notepack::{NoteBuf, pack_note};
+/// use notepack::{Error, NoteBuf, pack_note};
 ///
-/// let note = NoteBuf::default();
+/// let mut note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     ..NoteBuf::default()
+/// };
 /// let binary = pack_note(&note).unwrap();
 /// assert!(binary.len() > 0);
+///
+/// note.id.truncate(62);
+/// assert!(matches!(pack_note(&note), Err(Error::InvalidId)));
 /// ```
 pub fn pack_note(note: &NoteBuf) -> Result<Vec<u8>, Error> {
+    pack_note_with(note, &EncodeOptions::DEFAULT)
+}
+
+/// Like [`pack_note`], but with [`EncodeOptions`] controlling how tag elements
+/// are stored, e.g. [`EncodeOptions::NO_HEX_COMPRESSION`] to store every
+/// element as text.
+///
+/// # Errors
+///
+/// Returns the same [`Error`]s as [`pack_note`].
+pub fn pack_note_with(note: &NoteBuf, options: &EncodeOptions) -> Result<Vec<u8>, Error> {
     let mut buf = Vec::new();
+    write_note(note, options, &mut |bs| buf.extend_from_slice(bs))?;
+    Ok(buf)
+}
 
-    // version
-    write_varint(&mut buf, 1);
+/// Like [`pack_note`], but appends the payload to `out` instead of allocating.
+///
+/// Tag elements are hex-decoded through a small stack buffer, so reusing one
+/// `out` across many notes encodes without any per-note allocation once it
+/// has grown large enough.
+///
+/// # Errors
+///
+/// Returns the same [`Error`]s as [`pack_note`]. Nothing is appended on error.
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, encoded_len, pack_note, pack_note_into};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     content: "hi".into(),
+///     ..NoteBuf::default()
+/// };
+///
+/// let mut buf = Vec::with_capacity(encoded_len(&note));
+/// pack_note_into(&note, &mut buf).unwrap();
+/// assert_eq!(buf, pack_note(&note).unwrap());
+/// assert_eq!(buf.len(), encoded_len(&note));
+/// ```
+pub fn pack_note_into(note: &NoteBuf, out: &mut Vec<u8>) -> Result<(), Error> {
+    pack_note_into_with(note, &EncodeOptions::DEFAULT, out)
+}
+
+/// Like [`pack_note_into`], with [`EncodeOptions`] as in [`pack_note_with`].
+///
+/// # Errors
+///
+/// Returns the same [`Error`]s as [`pack_note`]. Nothing is appended on error.
+pub fn pack_note_into_with(
+    note: &NoteBuf,
+    options: &EncodeOptions,
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    write_note(note, options, &mut |bs| out.extend_from_slice(bs))
+}
 
-    // id
-    let id_bytes = hex::decode(&note.id)?;
-    buf.extend_from_slice(&id_bytes);
+/// Like [`pack_note`], but writes the payload to the start of `out` and
+/// returns its length. Use [`encoded_len`] to size `out`.
+///
+/// # Errors
+///
+/// Returns [`Error::BufferTooSmall`] if the payload does not fit, in addition
+/// to the errors of [`pack_note`].
+pub fn pack_note_to_slice(note: &NoteBuf, out: &mut [u8]) -> Result<usize, Error> {
+    pack_note_to_slice_with(note, &EncodeOptions::DEFAULT, out)
+}
 
-    // pubkey
-    let pk_bytes = hex::decode(&note.pubkey)?;
-    buf.extend_from_slice(&pk_bytes);
+/// Like [`pack_note_to_slice`], with [`EncodeOptions`] as in [`pack_note_with`].
+/// Use [`encoded_len_with`] with the same options to size `out`.
+///
+/// # Errors
+///
+/// Returns the same [`Error`]s as [`pack_note_to_slice`].
+pub fn pack_note_to_slice_with(
+    note: &NoteBuf,
+    options: &EncodeOptions,
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    let len = encoded_len_with(note, options);
+    let Some(out) = out.get_mut(..len) else {
+        return Err(Error::BufferTooSmall);
+    };
 
-    // signature
-    let sig_bytes = hex::decode(&note.sig)?;
-    buf.extend_from_slice(&sig_bytes);
+    let mut pos = 0;
+    write_note(note, options, &mut |bs| {
+        out[pos..pos + bs.len()].copy_from_slice(bs);
+        pos += bs.len();
+    })?;
+    Ok(pos)
+}
 
-    write_varint(&mut buf, note.created_at);
-    write_varint(&mut buf, note.kind);
-    write_varint(&mut buf, note.content.len() as u64);
-    buf.extend_from_slice(note.content.as_bytes());
+/// The exact size of the [`pack_note`] payload for `note`, without encoding it.
+///
+/// `id`, `pubkey` and `sig` are counted at their fixed sizes even if they are
+/// malformed, in which case packing fails anyway.
+pub fn encoded_len(note: &NoteBuf) -> usize {
+    encoded_len_with(note, &EncodeOptions::DEFAULT)
+}
 
-    write_varint(&mut buf, note.tags.len() as u64);
+/// The exact size of the [`pack_note_with`] payload for `note` and `options`.
+pub fn encoded_len_with(note: &NoteBuf, options: &EncodeOptions) -> usize {
+    let mut len = varint_len(NOTEPACK_VERSION as u64)
+        + 32
+        + 32
+        + 64
+        + varint_len(note.created_at)
+        + varint_len(note.kind)
+        + varint_len(note.content.len() as u64)
+        + note.content.len()
+        + varint_len(note.tags.len() as u64);
 
     for tag in &note.tags {
-        write_varint(&mut buf, tag.len() as u64);
+        len += varint_len(tag.len() as u64);
 
-        for elem in tag {
-            write_string(&mut buf, &elem);
+        let name = tag.first().map_or("", String::as_str);
+        for (i, elem) in tag.iter().enumerate() {
+            let payload = if options.stores_as_bytes(name, i, elem) {
+                elem.len() / 2
+            } else {
+                elem.len()
+            };
+            len += varint_len((payload as u64) << 1) + payload;
         }
     }
 
+    len
+}
+
+/// Encode `note`, handing the payload to `out` piece by piece.
+///
+/// All validation happens before the first call to `out`.
+fn write_note(
+    note: &NoteBuf,
+    options: &EncodeOptions,
+    out: &mut impl FnMut(&[u8]),
+) -> Result<(), Error> {
+    // fixed-size fields
+    let id: [u8; 32] = decode_fixed_hex(&note.id, Error::InvalidId)?;
+    let pubkey: [u8; 32] = decode_fixed_hex(&note.pubkey, Error::InvalidPubkey)?;
+    let sig: [u8; 64] = decode_fixed_hex(&note.sig, Error::InvalidSig)?;
+
+    // version
+    put_varint(out, NOTEPACK_VERSION as u64);
+
+    out(&id);
+    out(&pubkey);
+    out(&sig);
+
+    put_varint(out, note.created_at);
+    put_varint(out, note.kind);
+    put_varint(out, note.content.len() as u64);
+    out(note.content.as_bytes());
+
+    put_varint(out, note.tags.len() as u64);
+
+    for tag in &note.tags {
+        put_varint(out, tag.len() as u64);
+
+        let name = tag.first().map_or("", String::as_str);
+        for (i, elem) in tag.iter().enumerate() {
+            put_elem(out, elem, options.stores_as_bytes(name, i, elem));
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-encode a decoded [`Note`] straight from its bytes.
+///
+/// Unlike going through [`NoteBuf`], nothing is hex-encoded or re-decoded:
+/// fixed fields are copied as-is and every tag element keeps its
+/// [`StringType`], so a note that was packed with
+/// [`EncodeOptions::NO_HEX_COMPRESSION`] stays that way. Fields can be
+/// rewritten before packing, e.g. setting `tags` to [`Tags::default`] to
+/// strip them. [`Note::to_packed`] is the method form.
+///
+/// # Errors
+///
+/// Returns the [`Error`] from walking a malformed tags block.
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, NoteParser, Tags, pack_borrowed_note, pack_note};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     tags: vec![vec!["t".into(), "nostr".into()]],
+///     content: "hi".into(),
+///     ..NoteBuf::default()
+/// };
+/// let packed = pack_note(&note).unwrap();
+///
+/// let mut parsed = NoteParser::new(&packed).into_note().unwrap();
+/// assert_eq!(pack_borrowed_note(&parsed).unwrap(), packed);
+///
+/// parsed.tags = Tags::default();
+/// let stripped = pack_borrowed_note(&parsed).unwrap();
+/// assert!(NoteParser::new(&stripped).into_note().unwrap().tags.is_empty());
+/// ```
+pub fn pack_borrowed_note(note: &Note<'_>) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    write_borrowed_note(note, &mut |bs| buf.extend_from_slice(bs))?;
     Ok(buf)
 }
 
+/// Encode a decoded `note`, handing the payload to `out` piece by piece.
+fn write_borrowed_note(note: &Note<'_>, out: &mut impl FnMut(&[u8])) -> Result<(), Error> {
+    put_varint(out, NOTEPACK_VERSION as u64);
+
+    out(note.id);
+    out(note.pubkey);
+    out(note.sig);
+
+    put_varint(out, note.created_at);
+    put_varint(out, note.kind);
+    put_varint(out, note.content.len() as u64);
+    out(note.content.as_bytes());
+
+    let mut tags = note.tags.clone();
+    put_varint(out, tags.len());
+
+    while let Some(mut elems) = tags.next_tag()? {
+        put_varint(out, elems.remaining());
+
+        for elem in &mut elems {
+            match elem? {
+                StringType::Str(s) => {
+                    put_varint(out, (s.len() as u64) << 1);
+                    out(s.as_bytes());
+                }
+                StringType::Bytes(bs) => {
+                    put_varint(out, (bs.len() as u64) << 1 | 1);
+                    out(bs);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Like [`pack_note`], but unpacks the result again and checks that its
+/// canonical NIP-01 serialization matches `note`'s.
+///
+/// The event id is the hash of that serialization, so a note packed with this
+/// function is guaranteed to have the same id after unpacking.
+///
+/// # Errors
+///
+/// Returns [`Error::RoundTrip`] with the position of the first tag element
+/// that unpacks differently, in addition to the errors of [`pack_note`] and
+/// [`NoteBuf::canonical_json`].
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, pack_note, pack_note_checked};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     tags: vec![vec!["t".into(), "cafe".into()]],
+///     ..NoteBuf::default()
+/// };
+/// assert_eq!(pack_note_checked(&note).unwrap(), pack_note(&note).unwrap());
+/// ```
+pub fn pack_note_checked(note: &NoteBuf) -> Result<Vec<u8>, Error> {
+    pack_note_checked_with(note, &EncodeOptions::DEFAULT)
+}
+
+/// Like [`pack_note_checked`], but with [`EncodeOptions`] controlling how tag
+/// elements are stored.
+///
+/// # Errors
+///
+/// Returns the same [`Error`]s as [`pack_note_checked`].
+pub fn pack_note_checked_with(note: &NoteBuf, options: &EncodeOptions) -> Result<Vec<u8>, Error> {
+    let packed = pack_note_with(note, options)?;
+    check_round_trip(note, &packed)?;
+    Ok(packed)
+}
+
+/// Check that `packed` unpacks to the same canonical serialization as `note`.
+fn check_round_trip(note: &NoteBuf, packed: &[u8]) -> Result<(), Error> {
+    let decoded = NoteParser::new(packed).into_note()?;
+    let (mut want, mut got) = (Vec::new(), Vec::new());
+    note.canonical_json(&mut want)?;
+    decoded.canonical_json(&mut got)?;
+    if want == got {
+        return Ok(());
+    }
+
+    // find the first element that differs; past the last tag if none does
+    let tags = decoded.tags.to_strings()?;
+    let (tag, elem) = note
+        .tags
+        .iter()
+        .zip(&tags)
+        .enumerate()
+        .find_map(|(i, (sent, back))| {
+            let len = sent.len().max(back.len());
+            (0..len)
+                .find(|&j| sent.get(j) != back.get(j))
+                .map(|j| (i, j))
+        })
+        .unwrap_or((note.tags.len().min(tags.len()), 0));
+    Err(Error::RoundTrip { tag, elem })
+}
+
 /// Encodes a [`Note`] directly to a `notepack_...` Base64 string.
 ///
 /// This is a convenience wrapper around [`pack_note`], taking the binary payload and
@@ -162,20 +499,48 @@ pub fn pack_note(note: &NoteBuf) -> Result<Vec<u8>, Error> {
 ///
 /// # Errors
 ///
-/// Returns the same [`Error`]s as [`pack_note`], e.g. hex decoding issues.
+/// Returns the same [`Error`]s as [`pack_note`], e.g. a malformed id.
 ///
 /// # Example
 ///
 /// ```rust
 /// use notepack::{NoteBuf, pack_note_to_string};
 ///
-/// let note = NoteBuf::default();
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     ..NoteBuf::default()
+/// };
 /// let s = pack_note_to_string(&note).unwrap();
 /// assert!(s.starts_with("notepack_"));
 /// ```
 pub fn pack_note_to_string(note: &NoteBuf) -> Result<String, Error> {
-    let bytes = pack_note(note)?;
-    Ok(format!("notepack_{}", base64_encode(&bytes)))
+    Ok(packed_to_string(&pack_note(note)?))
+}
+
+/// Wrap an already-packed payload in the `notepack_...` Base64 string form.
+///
+/// This is the inverse of [`NoteParser::decode`].
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, NoteParser, pack_note, pack_note_to_string, packed_to_string};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     ..NoteBuf::default()
+/// };
+/// let packed = pack_note(&note).unwrap();
+/// let s = packed_to_string(&packed);
+/// assert_eq!(s, pack_note_to_string(&note).unwrap());
+/// assert_eq!(NoteParser::decode(&s).unwrap(), packed);
+/// ```
+pub fn packed_to_string(packed: &[u8]) -> String {
+    format!("notepack_{}", base64_encode(packed))
 }
 
 fn base64_encode(bs: &[u8]) -> String {
@@ -186,32 +551,350 @@ fn base64_encode(bs: &[u8]) -> String {
 
 /// Only lower cased hex are allowed, otherwise encoding
 /// wouldn't round-trip
-fn decode_lowercase_hex(input: &str) -> Result<Vec<u8>, Error> {
-    // Reject uppercase hex
-    if input.chars().any(|c| c.is_ascii_uppercase()) {
-        return Err(Error::FromHex);
+fn is_lowercase_hex(input: &str) -> bool {
+    input.len().is_multiple_of(2)
+        && input
+            .bytes()
+            .all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Decode a fixed-size field that must be exactly `N` bytes of lowercase hex.
+fn decode_fixed_hex<const N: usize>(input: &str, err: Error) -> Result<[u8; N], Error> {
+    let mut out = [0u8; N];
+    if input.len() != N * 2
+        || input.bytes().any(|c| c.is_ascii_uppercase())
+        || hex::decode_to_slice(input, &mut out).is_err()
+    {
+        return Err(err);
     }
+    Ok(out)
+}
 
-    // Reject odd-length hex strings
-    if input.len() % 2 != 0 {
-        return Err(Error::FromHex);
+fn put_varint(out: &mut impl FnMut(&[u8]), n: u64) {
+    let mut buf = [0u8; 10];
+    out(encode_varint(&mut buf, n));
+}
+
+/// Write a tag element with its header, hex-decoded if `as_bytes`.
+///
+/// `as_bytes` comes from [`EncodeOptions::stores_as_bytes`].
+fn put_elem(out: &mut impl FnMut(&[u8]), elem: &str, as_bytes: bool) {
+    if as_bytes {
+        put_varint(out, ((elem.len() / 2) as u64) << 1 | 1);
+        write_hex_bytes(out, elem);
+    } else {
+        put_varint(out, (elem.len() as u64) << 1);
+        out(elem.as_bytes());
     }
+}
 
-    Ok(hex::decode(input)?)
+/// Decode lowercase hex to `out` through a stack buffer.
+fn write_hex_bytes(out: &mut impl FnMut(&[u8]), hex: &str) {
+    let mut chunk = [0u8; 32];
+    for part in hex.as_bytes().chunks(chunk.len() * 2) {
+        let bytes = &mut chunk[..part.len() / 2];
+        hex::decode_to_slice(part, bytes).expect("checked by stores_as_bytes");
+        out(bytes);
+    }
 }
 
-fn write_string(buf: &mut Vec<u8>, string: &str) {
-    // we check to see if the entire string is 32-byte-hex
-    if string.is_empty() {
-        write_tagged_varint(buf, 0, false);
-        return;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::note;
+
+    fn pack_with(mutate: impl FnOnce(&mut NoteBuf)) -> Result<Vec<u8>, Error> {
+        let mut n = note();
+        mutate(&mut n);
+        pack_note(&n)
     }
 
-    if let Ok(val) = decode_lowercase_hex(string) {
-        write_tagged_varint(buf, val.len() as u64, true);
-        buf.extend_from_slice(&val);
-    } else {
-        write_tagged_varint(buf, string.len() as u64, false);
-        buf.extend_from_slice(string.as_bytes());
+    #[test]
+    fn pack_note_rejects_malformed_fixed_fields() {
+        assert!(pack_note(&note()).is_ok());
+
+        assert!(matches!(
+            pack_with(|n| n.id = "01".repeat(31)),
+            Err(Error::InvalidId)
+        ));
+        assert!(matches!(
+            pack_with(|n| n.id = "01".repeat(33)),
+            Err(Error::InvalidId)
+        ));
+        assert!(matches!(
+            pack_with(|n| n.id = "AB".repeat(32)),
+            Err(Error::InvalidId)
+        ));
+        assert!(matches!(
+            pack_with(|n| n.pubkey.clear()),
+            Err(Error::InvalidPubkey)
+        ));
+        assert!(matches!(
+            pack_with(|n| n.pubkey = "zz".repeat(32)),
+            Err(Error::InvalidPubkey)
+        ));
+        assert!(matches!(
+            pack_with(|n| n.sig = "03".repeat(32)),
+            Err(Error::InvalidSig)
+        ));
+    }
+
+    fn elem_kinds(packed: &[u8]) -> Vec<String> {
+        let parsed = NoteParser::new(packed).into_note().expect("parse");
+        let mut tags = parsed.tags.clone();
+        let mut out = Vec::new();
+        while let Some(elems) = tags.next_tag().expect("tag") {
+            for elem in elems {
+                out.push(match elem.expect("elem") {
+                    StringType::Str(s) => format!("S:{s}"),
+                    StringType::Bytes(bs) => format!("B:{}", hex::encode(bs)),
+                });
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn buffer_apis_match_pack_note() -> Result<(), Error> {
+        let mut n = note();
+        n.created_at = 1_720_000_000;
+        n.kind = 30_023;
+        n.content = "é".repeat(100);
+        n.tags = vec![
+            vec!["p".into(), "bb".repeat(32), "wss://relay".into()],
+            vec!["x".into(), "0f".repeat(100)],
+            vec!["t".into(), "".into(), "ABCD".into(), "abc".into()],
+            (0..200).map(|i| i.to_string()).collect(),
+        ];
+        let expected = pack_note(&n)?;
+        assert_eq!(encoded_len(&n), expected.len());
+
+        let mut buf = b"prefix".to_vec();
+        pack_note_into(&n, &mut buf)?;
+        assert_eq!(&buf[..6], b"prefix");
+        assert_eq!(&buf[6..], expected.as_slice());
+
+        let mut slice = vec![0xffu8; expected.len() + 3];
+        assert_eq!(pack_note_to_slice(&n, &mut slice)?, expected.len());
+        assert_eq!(&slice[..expected.len()], expected.as_slice());
+        assert_eq!(&slice[expected.len()..], &[0xff; 3]);
+
+        let mut short = vec![0u8; expected.len() - 1];
+        assert!(matches!(
+            pack_note_to_slice(&n, &mut short),
+            Err(Error::BufferTooSmall)
+        ));
+
+        // validation errors leave the buffer untouched
+        n.sig.clear();
+        let mut buf = Vec::new();
+        assert!(matches!(
+            pack_note_into(&n, &mut buf),
+            Err(Error::InvalidSig)
+        ));
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn buffer_apis_honor_encode_options() -> Result<(), Error> {
+        let mut n = note();
+        n.tags = vec![
+            vec!["p".into(), "bb".repeat(32)],
+            vec!["t".into(), "cafe".into(), "0f".repeat(100)],
+        ];
+
+        for options in [
+            EncodeOptions::DEFAULT,
+            EncodeOptions::NO_HEX_COMPRESSION,
+            EncodeOptions::references_only(),
+        ] {
+            let expected = pack_note_with(&n, &options)?;
+            assert_eq!(encoded_len_with(&n, &options), expected.len());
+
+            let mut buf = Vec::new();
+            pack_note_into_with(&n, &options, &mut buf)?;
+            assert_eq!(buf, expected);
+
+            let mut slice = vec![0u8; expected.len()];
+            assert_eq!(
+                pack_note_to_slice_with(&n, &options, &mut slice)?,
+                expected.len()
+            );
+            assert_eq!(slice, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn borrowed_notes_re_encode_without_hex() -> Result<(), Error> {
+        let mut n = note();
+        n.kind = 30_023;
+        n.content = "hello".into();
+        n.tags = vec![
+            vec!["e".into(), "aa".repeat(32)],
+            vec![],
+            vec!["t".into(), "cafe".into(), "".into()],
+        ];
+
+        for options in [EncodeOptions::DEFAULT, EncodeOptions::NO_HEX_COMPRESSION] {
+            let packed = pack_note_with(&n, &options)?;
+            let parsed = NoteParser::new(&packed).into_note()?;
+            assert_eq!(parsed.to_packed()?, packed);
+        }
+
+        let packed = pack_note(&n)?;
+        let mut parsed = NoteParser::new(&packed).into_note()?;
+        parsed.content = "rewritten";
+        n.content = "rewritten".into();
+        assert_eq!(parsed.to_packed()?, pack_note(&n)?);
+
+        // a truncated tags block surfaces the decode error
+        let mut parsed = NoteParser::new(&packed[..packed.len() - 2]).into_note()?;
+        let err = parsed.to_packed().unwrap_err();
+        assert!(matches!(err.root(), Error::Truncated));
+        parsed.tags = Tags::default();
+        n.content = "hello".into();
+        n.tags.clear();
+        assert_eq!(parsed.to_packed()?, pack_note(&n)?);
+        Ok(())
+    }
+
+    #[test]
+    fn varint_len_matches_encoding() {
+        for n in [0, 1, 127, 128, 16_383, 16_384, u64::MAX >> 1, u64::MAX] {
+            let mut buf = [0u8; 10];
+            assert_eq!(
+                varint::varint_len(n),
+                encode_varint(&mut buf, n).len(),
+                "{n}"
+            );
+        }
+    }
+
+    #[test]
+    fn pack_note_checked_matches_pack_note() -> Result<(), Error> {
+        let mut n = note();
+        n.content = "gm".into();
+        n.tags = vec![
+            vec!["p".into(), "bb".repeat(32)],
+            vec!["t".into(), "cafe".into(), "".into(), "CAFE".into()],
+        ];
+        assert_eq!(pack_note_checked(&n)?, pack_note(&n)?);
+        for options in [
+            EncodeOptions::NO_HEX_COMPRESSION,
+            EncodeOptions::references_only(),
+        ] {
+            assert_eq!(
+                pack_note_checked_with(&n, &options)?,
+                pack_note_with(&n, &options)?
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip_check_pinpoints_mismatches() -> Result<(), Error> {
+        let mut n = note();
+        n.tags = vec![
+            vec!["p".into(), "bb".repeat(32)],
+            vec!["t".into(), "cafe".into(), "".into()],
+        ];
+        let packed = pack_note(&n)?;
+
+        let check = |mutate: fn(&mut NoteBuf)| {
+            let mut other = n.clone();
+            mutate(&mut other);
+            match check_round_trip(&other, &packed) {
+                Err(Error::RoundTrip { tag, elem }) => (tag, elem),
+                other => panic!("expected a round-trip error, got {other:?}"),
+            }
+        };
+        assert_eq!(check(|o| o.tags[1][1] = "cafd".into()), (1, 1));
+        assert_eq!(check(|o| o.tags[0][1] = "BB".repeat(32)), (0, 1));
+        assert_eq!(
+            check(|o| {
+                o.tags[1].pop();
+            }),
+            (1, 2)
+        );
+        assert_eq!(check(|o| o.tags.push(vec!["t".into()])), (2, 0));
+        assert_eq!(check(|o| o.content.push('!')), (2, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn encode_options_narrow_the_hex_heuristic() {
+        let id = "aa".repeat(32);
+        let mut n = note();
+        n.tags = vec![
+            vec!["t".into(), "beef".into()],
+            vec!["e".into(), id.clone(), "cafe".into()],
+            vec!["x".into(), id.clone()],
+            vec!["p".into(), "beef".into()],
+        ];
+        let kinds = |options: EncodeOptions| {
+            let packed = pack_note_with(&n, &options).expect("pack");
+            elem_kinds(&packed).join(" ").replace(&id, "ID")
+        };
+
+        assert_eq!(
+            kinds(EncodeOptions::DEFAULT),
+            "S:t B:beef S:e B:ID B:cafe S:x B:ID S:p B:beef"
+        );
+        assert_eq!(
+            kinds(EncodeOptions {
+                min_hex_bytes: 32,
+                ..EncodeOptions::DEFAULT
+            }),
+            "S:t S:beef S:e B:ID S:cafe S:x B:ID S:p S:beef"
+        );
+        assert_eq!(
+            kinds(EncodeOptions::references_only()),
+            "S:t S:beef S:e B:ID S:cafe S:x S:ID S:p B:beef"
+        );
+        assert_eq!(
+            kinds(EncodeOptions {
+                min_hex_bytes: 32,
+                ..EncodeOptions::references_only()
+            }),
+            "S:t S:beef S:e B:ID S:cafe S:x S:ID S:p S:beef"
+        );
+        assert_eq!(
+            kinds(EncodeOptions::NO_HEX_COMPRESSION),
+            "S:t S:beef S:e S:ID S:cafe S:x S:ID S:p S:beef"
+        );
+    }
+
+    #[test]
+    fn no_hex_compression_keeps_hex_looking_text_as_text() {
+        let id = "aa".repeat(32);
+        let mut n = note();
+        n.tags = vec![
+            vec!["t".into(), "cafe".into()],
+            vec!["e".into(), id.clone()],
+        ];
+
+        assert_eq!(
+            elem_kinds(&pack_note(&n).expect("pack")),
+            ["S:t", "B:cafe", "S:e", &format!("B:{id}")]
+        );
+
+        let text = pack_note_with(&n, &EncodeOptions::NO_HEX_COMPRESSION).expect("pack");
+        assert_eq!(
+            elem_kinds(&text),
+            ["S:t", "S:cafe", "S:e", &format!("S:{id}")]
+        );
+
+        // both encodings hash to the same NIP-01 id
+        let default_id = NoteParser::new(&pack_note(&n).expect("pack"))
+            .into_note()
+            .and_then(|note| note.compute_id())
+            .expect("id");
+        let text_id = NoteParser::new(&text)
+            .into_note()
+            .and_then(|note| note.compute_id())
+            .expect("id");
+        assert_eq!(default_id, text_id);
     }
 }
@@ -0,0 +1,70 @@
+/// Upper bounds enforced while decoding untrusted notepack payloads.
+///
+/// Every length and count in notepack is an attacker-controlled varint, so
+/// anything accepting notes from the public should set these. Each limit is
+/// checked as soon as the corresponding length prefix is read, before any
+/// payload bytes are consumed.
+///
+/// The [`Default`] is [`DecodeLimits::UNLIMITED`], which matches the behavior
+/// of [`NoteParser::new`](crate::NoteParser::new).
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{DecodeLimits, Error, NoteBuf, NoteParser, pack_note};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     content: "x".repeat(100),
+///     ..NoteBuf::default()
+/// };
+/// let packed = pack_note(&note).unwrap();
+///
+/// let limits = DecodeLimits {
+///     max_content_bytes: 64,
+///     ..DecodeLimits::default()
+/// };
+/// let err = NoteParser::with_limits(&packed, limits).into_note().unwrap_err();
+/// assert!(matches!(err.root(), Error::ContentTooLarge));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum size of the whole notepack payload, in bytes.
+    pub max_total_size: u64,
+    /// Maximum length of `content`, in bytes.
+    pub max_content_bytes: u64,
+    /// Maximum number of tags.
+    pub max_tags: u64,
+    /// Maximum number of elements in a single tag.
+    pub max_elems_per_tag: u64,
+    /// Maximum length of a single tag element payload, in bytes.
+    pub max_elem_len: u64,
+}
+
+impl DecodeLimits {
+    /// No limits beyond what fits in the input.
+    pub const UNLIMITED: Self = Self {
+        max_total_size: u64::MAX,
+        max_content_bytes: u64::MAX,
+        max_tags: u64::MAX,
+        max_elems_per_tag: u64::MAX,
+        max_elem_len: u64::MAX,
+    };
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+/// Capacity to reserve for `count` items read from a payload with
+/// `bytes_left` bytes remaining.
+///
+/// Counts are untrusted, but every tag and element takes at least one byte,
+/// so nothing valid needs more than `bytes_left` slots.
+pub(crate) fn prealloc(count: u64, bytes_left: usize) -> usize {
+    count.min(bytes_left as u64) as usize
+}
@@ -1,4 +1,4 @@
-use notepack::{Error, NoteBuf, NoteParser, ParsedField, StringType, pack_note_to_string};
+use notepack::{Error, NoteBuf, NoteParser, json_to_notepack, packed_to_string};
 use std::io;
 
 fn main() -> Result<(), Error> {
@@ -14,59 +14,16 @@ fn main() -> Result<(), Error> {
             return Ok(());
         }
 
-        let parser = NoteParser::new(&packed);
-        let mut note = NoteBuf::default();
-        for field in parser {
-            process_field(&mut note, field?);
-        }
+        let note = NoteBuf::from_packed_bytes(&packed)?;
         println!("{}", serde_json::to_string(&note)?);
     } else {
-        let note: NoteBuf = serde_json::from_str(trimmed).expect("decode ok");
-        let packed = pack_note_to_string(&note).expect("packed ok");
-        println!("{packed}");
+        let packed = json_to_notepack(trimmed)?;
+        println!("{}", packed_to_string(&packed));
     }
 
     Ok(())
 }
 
-fn process_field(note: &mut NoteBuf, field: ParsedField<'_>) {
-    match field {
-        ParsedField::Version(_v) => {}
-        ParsedField::Id(id) => {
-            note.id = hex::encode(id);
-        }
-        ParsedField::Pubkey(pk) => {
-            note.pubkey = hex::encode(pk);
-        }
-        ParsedField::Sig(sig) => {
-            note.sig = hex::encode(sig);
-        }
-        ParsedField::CreatedAt(ts) => {
-            note.created_at = ts;
-        }
-        ParsedField::Kind(kind) => {
-            note.kind = kind;
-        }
-        ParsedField::Content(content) => {
-            note.content = content.to_string();
-        }
-        ParsedField::NumTags(n) => note.tags = Vec::with_capacity(n as usize),
-        ParsedField::NumTagElems(n) => note.tags.push(Vec::with_capacity(n as usize)),
-        ParsedField::Tag(tag) => {
-            let ind = note.tags.len() - 1;
-            let current = &mut note.tags[ind];
-            match tag {
-                StringType::Bytes(bs) => {
-                    current.push(hex::encode(bs));
-                }
-                StringType::Str(s) => {
-                    current.push(s.to_string());
-                }
-            }
-        }
-    }
-}
-
 /*
 fn print_field(field: ParsedField<'_>) {
     match field {
@@ -0,0 +1,103 @@
+use crate::{Archive, Error, NoteStream};
+use memmap2::Mmap;
+use std::fs::File;
+use std::path::Path;
+
+/// A read-only memory-mapped notepack file.
+///
+/// [`MappedFile::archive`] and [`MappedFile::stream`] hand out readers whose
+/// [`Note`](crate::Note)s, [`Tags`](crate::Tags) and
+/// [`StringType`](crate::StringType)s borrow straight from the mapping, so
+/// scanning a large file only pages in what is actually touched.
+///
+/// Requires the `mmap` feature.
+#[derive(Debug)]
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Memory-map the file at `path`.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped, by this
+    /// process or any other. Doing so is undefined behavior, since notes
+    /// borrow directly from the mapped memory.
+    pub unsafe fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        // SAFETY: upheld by the caller, see above.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Self { map })
+    }
+
+    /// The raw mapped bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Open the mapping as a notepack archive.
+    pub fn archive(&self) -> Result<Archive<'_>, Error> {
+        Archive::new(&self.map)
+    }
+
+    /// Open the mapping as a framed notepack stream.
+    ///
+    /// For files from untrusted sources, pass [`MappedFile::as_bytes`] to
+    /// [`NoteStream::with_limits`] instead.
+    pub fn stream(&self) -> Result<NoteStream<'_>, Error> {
+        NoteStream::new(&self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArchiveWriter, NoteBuf, NoteWriter, test_util};
+
+    fn note(id_byte: u8) -> NoteBuf {
+        NoteBuf {
+            id: hex::encode([id_byte; 32]),
+            content: format!("note {id_byte}"),
+            ..test_util::note()
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("notepack-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn mapped_archive_and_stream() -> Result<(), Error> {
+        let archive_path = temp_path("archive.npka");
+        let mut writer = ArchiveWriter::new(File::create(&archive_path)?)?;
+        writer.add_note(&note(7))?;
+        writer.add_note(&note(3))?;
+        writer.finish()?;
+
+        let stream_path = temp_path("stream.npks");
+        let mut writer = NoteWriter::new(File::create(&stream_path)?)?;
+        writer.write_note(&note(1))?;
+        writer.write_note(&note(2))?;
+        writer.flush()?;
+        drop(writer);
+
+        // SAFETY: the temp files are not modified while mapped.
+        let mapped = unsafe { MappedFile::open(&archive_path)? };
+        let archive = mapped.archive()?;
+        assert_eq!(archive.get(&[7; 32])?.expect("present").content, "note 7");
+
+        let mapped_stream = unsafe { MappedFile::open(&stream_path)? };
+        let contents: Vec<&str> = mapped_stream
+            .stream()?
+            .map(|n| n.map(|n| n.content))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(contents, vec!["note 1", "note 2"]);
+
+        drop(mapped);
+        drop(mapped_stream);
+        std::fs::remove_file(archive_path)?;
+        std::fs::remove_file(stream_path)?;
+        Ok(())
+    }
+}
@@ -1,9 +1,12 @@
 use crate::Error;
-use crate::parser::read_string;
+use crate::canonical::write_canonical;
+use crate::error::DecodeField;
+use crate::limits::{DecodeLimits, prealloc};
+use crate::parser::{check_limit, read_elem_header, read_string, read_uint};
 use crate::stringtype::StringType;
-use crate::varint::{read_tagged_varint, read_varint};
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct NoteBuf {
@@ -24,6 +27,69 @@ pub struct NoteBuf {
     pub sig: String,
 }
 
+impl NoteBuf {
+    /// Decode a packed note into hex strings.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`Error`] from [`NoteParser::into_note`](crate::NoteParser::into_note),
+    /// or from the first malformed tag element.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use notepack::{NoteBuf, pack_note, pack_note_to_string};
+    ///
+    /// let note = NoteBuf {
+    ///     id: "aa".repeat(32),
+    ///     pubkey: "bb".repeat(32),
+    ///     sig: "cc".repeat(64),
+    ///     tags: vec![vec!["t".into(), "nostr".into()]],
+    ///     ..NoteBuf::default()
+    /// };
+    ///
+    /// let decoded = NoteBuf::from_packed_bytes(&pack_note(&note).unwrap()).unwrap();
+    /// assert_eq!(decoded.tags, note.tags);
+    ///
+    /// let decoded = NoteBuf::from_notepack_str(&pack_note_to_string(&note).unwrap()).unwrap();
+    /// assert_eq!(decoded.sig, note.sig);
+    /// ```
+    pub fn from_packed_bytes(packed: &[u8]) -> Result<Self, Error> {
+        Self::try_from(crate::NoteParser::new(packed).into_note()?)
+    }
+
+    /// Decode a `notepack_...` Base64 string, see [`NoteBuf::from_packed_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`Error`] from [`NoteParser::decode`](crate::NoteParser::decode),
+    /// or any error from [`NoteBuf::from_packed_bytes`].
+    pub fn from_notepack_str(s: &str) -> Result<Self, Error> {
+        Self::from_packed_bytes(&crate::NoteParser::decode(s)?)
+    }
+
+    /// Append the canonical NIP-01 serialization of this note to `out`.
+    ///
+    /// See [`Note::canonical_json`]; both produce the same bytes for the same
+    /// note, whether or not its hex tag elements were packed as bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidPubkey`] if `pubkey` is not 32 bytes of
+    /// lowercase hex. Nothing is appended on error.
+    pub fn canonical_json(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let pubkey: [u8; 32] = crate::decode_fixed_hex(&self.pubkey, Error::InvalidPubkey)?;
+        write_canonical(
+            &mut |bs| out.extend_from_slice(bs),
+            &pubkey,
+            self.created_at,
+            self.kind,
+            self.tags.as_slice(),
+            &self.content,
+        )
+    }
+}
+
 /// a Nostr note in notepack format
 #[derive(Debug, Clone)]
 pub struct Note<'a> {
@@ -44,6 +110,152 @@ pub struct Note<'a> {
     pub tags: Tags<'a>,
 }
 
+impl<'a> Note<'a> {
+    /// notepack format version the note was decoded from
+    ///
+    /// [`NoteParser::into_note`](crate::NoteParser::into_note) rejects every
+    /// version it has no layout for, so this is currently always
+    /// [`NOTEPACK_VERSION`](crate::NOTEPACK_VERSION).
+    pub fn version(&self) -> u8 {
+        crate::NOTEPACK_VERSION
+    }
+
+    /// Append the canonical NIP-01 serialization
+    /// `[0,"<pubkey>",<created_at>,<kind>,<tags>,"<content>"]` to `out`.
+    ///
+    /// This is the input to the event id hash. Strings use the NIP-01 escaping
+    /// rules, which only escape `\n`, `"`, `\\`, `\r`, `\t`, backspace and form
+    /// feed, and [`StringType::Bytes`] tag elements are written as the same
+    /// lowercase hex they were packed from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if the tags block is malformed. Nothing is
+    /// appended on error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use notepack::{NoteBuf, NoteParser, pack_note};
+    ///
+    /// let note = NoteBuf {
+    ///     id: "aa".repeat(32),
+    ///     pubkey: "bb".repeat(32),
+    ///     sig: "cc".repeat(64),
+    ///     kind: 1,
+    ///     tags: vec![vec!["t".into(), "nostr".into()]],
+    ///     content: "hi".into(),
+    ///     ..NoteBuf::default()
+    /// };
+    /// let packed = pack_note(&note).unwrap();
+    /// let parsed = NoteParser::new(&packed).into_note().unwrap();
+    ///
+    /// let mut buf = Vec::new();
+    /// parsed.canonical_json(&mut buf).unwrap();
+    /// let expected = format!(r#"[0,"{}",0,1,[["t","nostr"]],"hi"]"#, "bb".repeat(32));
+    /// assert_eq!(buf, expected.as_bytes());
+    ///
+    /// let mut from_buf = Vec::new();
+    /// note.canonical_json(&mut from_buf).unwrap();
+    /// assert_eq!(from_buf, buf);
+    /// ```
+    pub fn canonical_json(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let start = out.len();
+        self.write_canonical_json(&mut |bs| out.extend_from_slice(bs))
+            .inspect_err(|_| out.truncate(start))
+    }
+
+    /// Stream the [canonical serialization](Note::canonical_json) to `out`.
+    ///
+    /// Both [`Note::canonical_json`] and [`Note::compute_id`] go through
+    /// this, so the bytes that are hashed are always the bytes returned.
+    fn write_canonical_json(&self, out: &mut impl FnMut(&[u8])) -> Result<(), Error> {
+        write_canonical(
+            out,
+            self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            self.content,
+        )
+    }
+
+    /// Compute the NIP-01 event id of this note.
+    ///
+    /// This is the sha256 of the [canonical serialization](Note::canonical_json)
+    /// `[0,pubkey,created_at,kind,tags,content]`. The tags are walked lazily
+    /// and [`StringType::Bytes`] elements are hex-encoded straight into the
+    /// hasher, so no [`NoteBuf`] is materialized.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if the tags block is malformed.
+    pub fn compute_id(&self) -> Result<[u8; 32], Error> {
+        let mut hasher = Sha256::new();
+        self.write_canonical_json(&mut |bs| hasher.update(bs))?;
+        Ok(hasher.finalize().into())
+    }
+
+    /// Check that [`Note::id`] matches the id computed from the note's contents.
+    ///
+    /// See [`Note::compute_id`].
+    pub fn verify_id(&self) -> Result<bool, Error> {
+        Ok(&self.compute_id()? == self.id)
+    }
+
+    /// Re-encode this note without going through hex.
+    ///
+    /// See [`pack_borrowed_note`](crate::pack_borrowed_note).
+    pub fn to_packed(&self) -> Result<Vec<u8>, Error> {
+        crate::pack_borrowed_note(self)
+    }
+
+    /// Check the BIP-340 Schnorr signature in [`Note::sig`] against
+    /// [`Note::pubkey`] and [`Note::id`].
+    ///
+    /// This only looks at the fixed-size fields, so forgeries can be rejected
+    /// before tags or content are touched. It does **not** check that the id
+    /// matches the note's contents; pair it with [`Note::verify_id`] for that.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Secp256k1`] if the pubkey is not a valid x-only key.
+    #[cfg(feature = "secp256k1")]
+    pub fn verify_signature(&self) -> Result<bool, Error> {
+        use secp256k1::{SECP256K1, XOnlyPublicKey, schnorr::Signature};
+
+        let pubkey = XOnlyPublicKey::from_byte_array(self.pubkey)?;
+        let sig = Signature::from_byte_array(*self.sig);
+
+        Ok(SECP256K1.verify_schnorr(&sig, self.id, &pubkey).is_ok())
+    }
+}
+
+impl TryFrom<&Note<'_>> for NoteBuf {
+    type Error = Error;
+
+    /// Hex-encode the fixed fields and [`StringType::Bytes`] tag elements.
+    fn try_from(note: &Note<'_>) -> Result<Self, Error> {
+        Ok(NoteBuf {
+            id: hex::encode(note.id),
+            pubkey: hex::encode(note.pubkey),
+            created_at: note.created_at,
+            kind: note.kind,
+            tags: note.tags.to_strings()?,
+            content: note.content.to_string(),
+            sig: hex::encode(note.sig),
+        })
+    }
+}
+
+impl TryFrom<Note<'_>> for NoteBuf {
+    type Error = Error;
+
+    fn try_from(note: Note<'_>) -> Result<Self, Error> {
+        Self::try_from(&note)
+    }
+}
+
 impl<'a> Serialize for Note<'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -59,27 +271,10 @@ impl<'a> Serialize for Note<'a> {
         st.serialize_field("kind", &self.kind)?;
 
         // Materialize tags to Vec<Vec<String>> for JSON.
-        // Strings pass through; raw bytes become lowercase hex strings.
-        let mut tags_json: Vec<Vec<String>> = Vec::with_capacity(self.tags.len() as usize);
-        let mut tags = self.tags.clone(); // don't mutate self
-
-        while let Some(mut elems) = tags
-            .next_tag()
-            .map_err(|e| <S::Error as serde::ser::Error>::custom(e.to_string()))?
-        {
-            let mut tag_vec: Vec<String> = Vec::with_capacity(elems.remaining() as usize);
-            while let Some(elem) = elems
-                .next()
-                .transpose()
-                .map_err(|e| <S::Error as serde::ser::Error>::custom(e.to_string()))?
-            {
-                match elem {
-                    crate::stringtype::StringType::Str(s) => tag_vec.push(s.to_string()),
-                    crate::stringtype::StringType::Bytes(bs) => tag_vec.push(hex::encode(bs)),
-                }
-            }
-            tags_json.push(tag_vec);
-        }
+        let tags_json = self
+            .tags
+            .to_strings()
+            .map_err(|e| <S::Error as serde::ser::Error>::custom(e.to_string()))?;
 
         st.serialize_field("tags", &tags_json)?;
         st.serialize_field("content", &self.content)?;
@@ -103,7 +298,7 @@ impl<'a> Serialize for Note<'a> {
 /// ```rust
 /// # use notepack::{NoteParser, StringType};
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// let packed = NoteParser::decode("notepack_737yskaxtaKQSL3IPPhOOR8T1R4G/f4ARPHGeNPfOpF4417q9YtU+4JZGOD3+Y0S3uVU6/edo64oTqJQ0pOF29Ms7GmX6fzM4Wjc6sohGPlbdRGLjhuqIRccETX5DliwUFy9qGg2lDD9oMl8ijoNFq4wwJ5Ikmr4Vh7NYWBwOkuo/anEBgECaGkA")?;
+/// let packed = NoteParser::decode("notepack_AfHnvCqXVkU/zA6A7PYhg/qVuaEnigEoHbwxC2d3Mg6Af+Q321iE7gE/cBp1+NGoTstDTpl/KjFBFoVVH//xuEF1UH+E14IRpo8vlkIh9Vh6qVembBlB0BElyqB7mqvfWpjD5j0f4eMHy/AbdLChuV/+Y262dGwAFn4NSOWxEDLVlsmpxAYBAmhpAA")?;
 /// let note = NoteParser::new(&packed).into_note()?;
 /// let mut tags = note.tags.clone();
 ///
@@ -122,10 +317,32 @@ impl<'a> Serialize for Note<'a> {
 ///
 /// - Dropping a [`TagElems`] early will fast-forward to the next tag automatically.
 /// - Use [`TagElems::finish()`] to explicitly surface errors from any skipped elements.
-#[derive(Debug, Clone)]
+/// - Errors carry the tag/element index and byte offset, see [`Error::At`].
+/// - [`Tags::default`] is an empty tags block.
+#[derive(Debug, Clone, Default)]
 pub struct Tags<'a> {
-    data: &'a [u8], // cursor: at the next tag's num_elems varint
-    remaining: u64, // tags left
+    data: &'a [u8],    // cursor: at the next tag's num_elems varint
+    end: usize,        // payload offset just past `data`
+    index: u64,        // index of the next tag
+    remaining: u64,    // tags left
+    max_elems: u64,    // DecodeLimits::max_elems_per_tag
+    max_elem_len: u64, // DecodeLimits::max_elem_len
+    strict: bool,      // NoteParser::strict
+}
+
+/// One step of [`Tags::walk`].
+pub(crate) enum TagEvent<'a> {
+    /// The `tag`-th remaining tag starts and claims `elems` elements, with
+    /// `bytes_left` bytes of the payload after its header.
+    Start {
+        tag: u64,
+        elems: u64,
+        bytes_left: usize,
+    },
+    /// Element `elem` of the current tag.
+    Elem { elem: u64, value: StringType<'a> },
+    /// The current tag has ended.
+    End,
 }
 
 /// A lazy iterator over the elements of a single tag.
@@ -147,7 +364,7 @@ pub struct Tags<'a> {
 /// ```rust
 /// # use notepack::{NoteParser, StringType};
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// let bytes = NoteParser::decode("notepack_737yskaxtaKQSL3IPPhOOR8T1R4G/f4ARPHGeNPfOpF4417q9YtU+4JZGOD3+Y0S3uVU6/edo64oTqJQ0pOF29Ms7GmX6fzM4Wjc6sohGPlbdRGLjhuqIRccETX5DliwUFy9qGg2lDD9oMl8ijoNFq4wwJ5Ikmr4Vh7NYWBwOkuo/anEBgECaGkA")?;
+/// let bytes = NoteParser::decode("notepack_AfHnvCqXVkU/zA6A7PYhg/qVuaEnigEoHbwxC2d3Mg6Af+Q321iE7gE/cBp1+NGoTstDTpl/KjFBFoVVH//xuEF1UH+E14IRpo8vlkIh9Vh6qVembBlB0BElyqB7mqvfWpjD5j0f4eMHy/AbdLChuV/+Y262dGwAFn4NSOWxEDLVlsmpxAYBAmhpAA")?;
 /// let note = NoteParser::new(&bytes).into_note()?;
 /// let mut tags = note.tags.clone();
 ///
@@ -164,7 +381,12 @@ pub struct Tags<'a> {
 #[derive(Debug)]
 pub struct TagElems<'a, 'p> {
     cursor: &'p mut &'a [u8], // shared cursor with parent
+    end: usize,               // payload offset just past `cursor`
+    tag: u64,                 // index of this tag
+    elem: u64,                // index of the next element
     remaining: u64,           // elements left in this tag
+    max_elem_len: u64,        // DecodeLimits::max_elem_len
+    strict: bool,             // NoteParser::strict
 }
 
 impl<'a> Tags<'a> {
@@ -174,13 +396,105 @@ impl<'a> Tags<'a> {
     /// On success, this consumes that varint and returns a cursor positioned at the
     /// first tag’s `num_elems`.
     pub fn parse(input: &mut &'a [u8]) -> Result<Self, Error> {
-        let num_tags = read_varint(input)?;
+        Self::parse_with_limits(input, &DecodeLimits::UNLIMITED)
+    }
+
+    /// Like [`Tags::parse`], but enforces the tag-related [`DecodeLimits`].
+    ///
+    /// `max_tags` is checked here; `max_elems_per_tag` and `max_elem_len` are
+    /// checked lazily by [`Tags::next_tag`] and [`TagElems`].
+    ///
+    /// Error offsets are relative to the start of `input`.
+    pub fn parse_with_limits(input: &mut &'a [u8], limits: &DecodeLimits) -> Result<Self, Error> {
+        Self::parse_at(input, 0, limits, false)
+    }
+
+    /// Like [`Tags::parse_with_limits`], where `input` starts at `offset` in the payload.
+    pub(crate) fn parse_at(
+        input: &mut &'a [u8],
+        offset: usize,
+        limits: &DecodeLimits,
+        strict: bool,
+    ) -> Result<Self, Error> {
+        let end = offset + input.len();
+        let num_tags = read_uint(input, strict)
+            .and_then(|n| check_limit(n, limits.max_tags, Error::TooManyTags).map(|_| n))
+            .map_err(|e| e.at(offset, DecodeField::NumTags))?;
         Ok(Self {
             data: *input,
+            end,
+            index: 0,
             remaining: num_tags,
+            max_elems: limits.max_elems_per_tag,
+            max_elem_len: limits.max_elem_len,
+            strict,
         })
     }
 
+    /// Walk every remaining tag, checking that the block ends exactly at the
+    /// end of the payload.
+    pub(crate) fn validate_to_end(mut self) -> Result<(), Error> {
+        while let Some(elems) = self.next_tag()? {
+            elems.finish()?;
+        }
+        if !self.data.is_empty() {
+            return Err(Error::TrailingBytes.at(self.offset(), DecodeField::End));
+        }
+        Ok(())
+    }
+
+    /// Byte offset of the next tag in the payload.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.end - self.data.len()
+    }
+
+    /// Materialize the remaining tags as strings, with raw bytes as lowercase hex.
+    pub(crate) fn to_strings(&self) -> Result<Vec<Vec<String>>, Error> {
+        let mut out = Vec::with_capacity(prealloc(self.remaining, self.data.len()));
+
+        self.walk(|event| match event {
+            TagEvent::Start {
+                elems, bytes_left, ..
+            } => out.push(Vec::with_capacity(prealloc(elems, bytes_left))),
+            TagEvent::Elem { value, .. } => {
+                let tag = out.last_mut().expect("inside a tag");
+                tag.push(match value {
+                    StringType::Str(s) => s.to_string(),
+                    StringType::Bytes(bs) => hex::encode(bs),
+                });
+            }
+            TagEvent::End => {}
+        })?;
+        Ok(out)
+    }
+
+    /// Walk the remaining tags without consuming `self`, handing each tag
+    /// boundary and element to `visit` in order.
+    ///
+    /// This is the one tag loop behind [`Tags::to_strings`] and the JSON
+    /// writers; it stops at the first malformed tag or element.
+    pub(crate) fn walk(&self, mut visit: impl FnMut(TagEvent<'a>)) -> Result<(), Error> {
+        let mut tags = self.clone();
+        let mut tag = 0;
+        while let Some(mut elems) = tags.next_tag()? {
+            visit(TagEvent::Start {
+                tag,
+                elems: elems.remaining(),
+                bytes_left: elems.cursor.len(),
+            });
+            for (elem, value) in (&mut elems).enumerate() {
+                visit(TagEvent::Elem {
+                    elem: elem as u64,
+                    value: value?,
+                });
+            }
+            visit(TagEvent::End);
+            tag += 1;
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn len(&self) -> u64 {
         self.remaining
@@ -201,11 +515,21 @@ impl<'a> Tags<'a> {
             return Ok(None);
         }
         // Read this tag's num_elems; leave cursor at the first element.
-        let num_elems = read_varint(&mut self.data)?;
+        let offset = self.offset();
+        let tag = self.index;
+        let num_elems = read_uint(&mut self.data, self.strict)
+            .and_then(|n| check_limit(n, self.max_elems, Error::TooManyTagElems).map(|_| n))
+            .map_err(|e| e.at(offset, DecodeField::NumTagElems { tag }))?;
         self.remaining -= 1;
+        self.index += 1;
         Ok(Some(TagElems {
             cursor: &mut self.data,
+            end: self.end,
+            tag,
+            elem: 0,
             remaining: num_elems,
+            max_elem_len: self.max_elem_len,
+            strict: self.strict,
         }))
     }
 }
@@ -216,19 +540,54 @@ impl<'a, 'p> TagElems<'a, 'p> {
         self.remaining
     }
 
+    /// Byte offset of the next element in the payload.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.end - self.cursor.len()
+    }
+
+    /// Index of this tag within the note's tags.
+    #[inline]
+    pub fn tag_index(&self) -> u64 {
+        self.tag
+    }
+
+    /// Index of the next element within this tag.
+    #[inline]
+    pub fn elem_index(&self) -> u64 {
+        self.elem
+    }
+
+    /// Annotate `err` with the position of the next element.
+    fn context(&self, err: Error) -> Error {
+        err.at(
+            self.offset(),
+            DecodeField::TagElem {
+                tag: self.tag,
+                elem: self.elem,
+            },
+        )
+    }
+
     /// Explicitly finish (skip any remaining elements).
     /// Prefer this if you want errors surfaced instead of silent best‑effort in Drop.
     pub fn finish(mut self) -> Result<(), Error> {
         while self.remaining > 0 {
-            let (len, _is_bytes) = read_tagged_varint(self.cursor)?;
-            if self.cursor.len() < len as usize {
-                return Err(Error::Truncated);
-            }
-            *self.cursor = &self.cursor[len as usize..];
-            self.remaining -= 1;
+            self.skip_elem()?;
         }
         Ok(())
     }
+
+    /// Skip one element without validating its contents.
+    fn skip_elem(&mut self) -> Result<(), Error> {
+        let mut input = *self.cursor;
+        let (len, _is_bytes) = read_elem_header(&mut input, self.max_elem_len, self.strict)
+            .map_err(|e| self.context(e))?;
+        *self.cursor = &input[len..];
+        self.remaining -= 1;
+        self.elem += 1;
+        Ok(())
+    }
 }
 
 impl<'a, 'p> Iterator for TagElems<'a, 'p> {
@@ -239,16 +598,20 @@ impl<'a, 'p> Iterator for TagElems<'a, 'p> {
             return None;
         }
         // Read one tagged string and advance the shared cursor.
-        let item = read_string(self.cursor);
+        let mut input = *self.cursor;
+        let item = read_string(&mut input, self.max_elem_len, self.strict);
         match item {
             Ok(s) => {
+                *self.cursor = input;
                 self.remaining -= 1;
+                self.elem += 1;
                 Some(Ok(s))
             }
             Err(e) => {
-                // Poison the iterator; parent cursor is wherever the error occurred.
+                // Poison the iterator; parent cursor stays at the bad element.
+                let err = self.context(e);
                 self.remaining = 0;
-                Some(Err(e))
+                Some(Err(err))
             }
         }
     }
@@ -261,14 +624,8 @@ impl<'a, 'p> Drop for TagElems<'a, 'p> {
     fn drop(&mut self) {
         // If fully drained, do nothing.
         while self.remaining > 0 {
-            if let Ok((len, _is_bytes)) = read_tagged_varint(self.cursor) {
-                if self.cursor.len() < len as usize {
-                    break; // truncated; leave cursor as-is
-                }
-                *self.cursor = &self.cursor[len as usize..];
-                self.remaining -= 1;
-            } else {
-                break; // malformed; leave cursor as-is
+            if self.skip_elem().is_err() {
+                break; // malformed or truncated; leave cursor as-is
             }
         }
     }
@@ -336,7 +693,7 @@ mod tests {
         {
             let mut t0 = tags.next_tag()?.expect("tag0");
             let mut out = Vec::new();
-            while let Some(x) = t0.next() {
+            for x in &mut t0 {
                 match x? {
                     StringType::Str(s) => out.push(format!("S:{s}")),
                     StringType::Bytes(bs) => out.push(format!("B:{}", hex::encode(bs))),
@@ -416,9 +773,194 @@ mod tests {
 
         // Using finish() should surface the error
         let err = elems.finish().unwrap_err();
-        match err {
+        match err.root() {
             Error::Truncated => {} // expected
             other => panic!("unexpected error: {other:?}"),
         }
     }
+
+    #[test]
+    fn errors_point_at_tag_and_element() -> Result<(), Error> {
+        // tag0: ["a"], tag1: ["p", <truncated>]
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 2);
+        write_varint(&mut buf, 1);
+        push_elem_str(&mut buf, "a");
+        write_varint(&mut buf, 2);
+        push_elem_str(&mut buf, "p");
+        let bad_at = buf.len();
+        write_tagged_varint(&mut buf, 10, false);
+        buf.extend_from_slice(b"abc");
+
+        let mut input = buf.as_slice();
+        let mut tags = Tags::parse(&mut input)?;
+        tags.next_tag()?.expect("tag0").finish()?;
+        assert_eq!(tags.offset(), 4);
+
+        let mut t1 = tags.next_tag()?.expect("tag1");
+        assert_eq!((t1.tag_index(), t1.elem_index(), t1.offset()), (1, 0, 5));
+        t1.next().expect("p")?;
+        assert_eq!((t1.elem_index(), t1.offset()), (1, bad_at));
+
+        let err = t1.next().expect("bad elem").unwrap_err();
+        assert!(matches!(err.root(), Error::Truncated));
+        assert_eq!(err.offset(), Some(bad_at));
+        assert_eq!(err.field(), Some(DecodeField::TagElem { tag: 1, elem: 1 }));
+        assert_eq!(
+            err.to_string(),
+            "tag 1 element 1 at offset 7: notepack string is truncated"
+        );
+        Ok(())
+    }
+
+    fn spec_note() -> NoteBuf {
+        NoteBuf {
+            id: "77b88d05559aff78be6dff7bf59b76301e6977aaf81fe629cb0bf787135f8124".into(),
+            pubkey: "11".repeat(32),
+            created_at: 1720000000,
+            kind: 1,
+            tags: vec![
+                vec![
+                    "e".into(),
+                    "aa".repeat(32),
+                    "wss://relay.example.com".into(),
+                ],
+                vec!["p".into(), "bb".repeat(32)],
+            ],
+            content: "hello".into(),
+            sig: "22".repeat(64),
+        }
+    }
+
+    #[test]
+    fn compute_id_hashes_canonical_serialization() -> Result<(), Error> {
+        let note = spec_note();
+        let packed = crate::pack_note(&note)?;
+        let parsed = crate::NoteParser::new(&packed).into_note()?;
+
+        assert_eq!(hex::encode(parsed.compute_id()?), note.id);
+        assert!(parsed.verify_id()?);
+        Ok(())
+    }
+
+    #[test]
+    fn canonical_json_agrees_between_note_and_notebuf() -> Result<(), Error> {
+        let mut note = spec_note();
+        note.tags.push(vec!["t".into(), "cafe".into(), "".into()]);
+        note.content = "line\nquote\" \u{1}".into();
+        let packed = crate::pack_note(&note)?;
+        let parsed = crate::NoteParser::new(&packed).into_note()?;
+
+        let mut from_note = b"prefix".to_vec();
+        parsed.canonical_json(&mut from_note)?;
+        let mut from_buf = b"prefix".to_vec();
+        note.canonical_json(&mut from_buf)?;
+        assert_eq!(from_note, from_buf);
+        assert_eq!(
+            parsed.compute_id()?,
+            <[u8; 32]>::from(Sha256::digest(&from_note[6..]))
+        );
+
+        note.pubkey = "AA".repeat(32);
+        let mut out = Vec::new();
+        assert!(matches!(
+            note.canonical_json(&mut out),
+            Err(Error::InvalidPubkey)
+        ));
+        assert!(out.is_empty());
+
+        let truncated = crate::NoteParser::new(&packed[..packed.len() - 1]).into_note()?;
+        let mut out = Vec::new();
+        assert!(truncated.canonical_json(&mut out).is_err());
+        assert!(out.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn notebuf_from_packed_surfaces_tag_errors() -> Result<(), Error> {
+        let note = spec_note();
+        let packed = crate::pack_note(&note)?;
+        let decoded = NoteBuf::from_packed_bytes(&packed)?;
+        assert_eq!(decoded.tags, note.tags);
+        assert_eq!(crate::pack_note(&decoded)?, packed);
+
+        // a huge claimed tag count must not be trusted for preallocation
+        let mut lying = crate::pack_note(&NoteBuf {
+            tags: vec![],
+            ..spec_note()
+        })?;
+        lying.pop();
+        write_varint(&mut lying, u64::MAX);
+        let err = NoteBuf::from_packed_bytes(&lying).unwrap_err();
+        assert!(matches!(err.root(), Error::VarintUnterminated));
+        assert_eq!(err.field(), Some(DecodeField::NumTagElems { tag: 0 }));
+        Ok(())
+    }
+
+    #[test]
+    fn verify_id_rejects_tampered_content() -> Result<(), Error> {
+        let mut note = spec_note();
+        note.content = "hellO".into();
+        let packed = crate::pack_note(&note)?;
+        let parsed = crate::NoteParser::new(&packed).into_note()?;
+
+        assert!(!parsed.verify_id()?);
+        Ok(())
+    }
+
+    #[cfg(feature = "secp256k1")]
+    fn signed_note() -> NoteBuf {
+        use secp256k1::{Keypair, SECP256K1};
+
+        let keypair = Keypair::from_seckey_slice(SECP256K1, &[0x01; 32]).expect("seckey");
+        let mut note = spec_note();
+        note.pubkey = hex::encode(keypair.x_only_public_key().0.serialize());
+
+        let packed = crate::pack_note(&note).expect("pack ok");
+        let id = crate::NoteParser::new(&packed)
+            .into_note()
+            .expect("parse ok")
+            .compute_id()
+            .expect("id ok");
+        note.id = hex::encode(id);
+        note.sig = hex::encode(
+            SECP256K1
+                .sign_schnorr_no_aux_rand(&id, &keypair)
+                .to_byte_array(),
+        );
+        note
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn verify_signature_accepts_valid_and_rejects_forged() -> Result<(), Error> {
+        let note = signed_note();
+        let packed = crate::pack_note(&note)?;
+        let parsed = crate::NoteParser::new(&packed).into_note()?;
+        assert!(parsed.verify_id()?);
+        assert!(parsed.verify_signature()?);
+
+        let mut forged = note.clone();
+        forged.sig.replace_range(0..2, "00");
+        let packed = crate::pack_note(&forged)?;
+        let parsed = crate::NoteParser::new(&packed).into_note()?;
+        assert!(!parsed.verify_signature()?);
+        Ok(())
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn verify_signature_errors_on_invalid_pubkey() -> Result<(), Error> {
+        let mut note = signed_note();
+        // x = 0 is not on the curve
+        note.pubkey = "00".repeat(32);
+        let packed = crate::pack_note(&note)?;
+        let parsed = crate::NoteParser::new(&packed).into_note()?;
+
+        assert!(matches!(
+            parsed.verify_signature(),
+            Err(Error::Secp256k1(_))
+        ));
+        Ok(())
+    }
 }
@@ -0,0 +1,100 @@
+/// Controls how [`pack_note_with`](crate::pack_note_with) encodes tag elements.
+///
+/// By default any lowercase, even-length hex tag element is stored as raw
+/// bytes. The fields below narrow that heuristic: require a minimum length,
+/// restrict it to known positions such as the id in `["e", <id>]`, or turn it
+/// off entirely.
+///
+/// Decoding a [`StringType::Bytes`](crate::StringType::Bytes) element always
+/// yields its lowercase hex, which is exactly the text it was packed from, so
+/// strings and the NIP-01 id survive either way. What the default encoding
+/// loses is *intent*: a decoder sees `["t","cafe"]` as a tag holding two raw
+/// bytes. [`EncodeOptions::NO_HEX_COMPRESSION`] turns the heuristic off, so
+/// every element is stored and decoded as text, at the cost of ids and
+/// pubkeys in tags taking twice the space. See `SPEC.md` §6.
+///
+/// The [`Default`] is [`EncodeOptions::DEFAULT`], which matches
+/// [`pack_note`](crate::pack_note).
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{EncodeOptions, NoteBuf, NoteParser, StringType, pack_note_with};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     tags: vec![vec!["t".into(), "cafe".into()]],
+///     ..NoteBuf::default()
+/// };
+///
+/// let packed = pack_note_with(&note, &EncodeOptions::NO_HEX_COMPRESSION).unwrap();
+/// let parsed = NoteParser::new(&packed).into_note().unwrap();
+/// let mut tags = parsed.tags.clone();
+/// let mut t = tags.next_tag().unwrap().unwrap();
+/// t.next();
+/// assert!(matches!(t.next(), Some(Ok(StringType::Str("cafe")))));
+///
+/// // only hex of at least 32 bytes, e.g. ids and pubkeys, becomes bytes
+/// let options = EncodeOptions {
+///     min_hex_bytes: 32,
+///     ..EncodeOptions::DEFAULT
+/// };
+/// assert_eq!(pack_note_with(&note, &options).unwrap(), packed);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Store lowercase, even-length hex tag elements as raw bytes.
+    pub hex_as_bytes: bool,
+    /// Minimum decoded length, in bytes, for a hex element to be stored as bytes.
+    pub min_hex_bytes: usize,
+    /// If set, only elements at these `(tag name, element index)` positions
+    /// are stored as bytes. The tag name is element 0.
+    pub hex_positions: Option<Vec<(String, usize)>>,
+}
+
+impl EncodeOptions {
+    /// The reference encoding: hex-looking tag elements are stored as bytes.
+    pub const DEFAULT: Self = Self {
+        hex_as_bytes: true,
+        min_hex_bytes: 1,
+        hex_positions: None,
+    };
+
+    /// Every tag element is stored as text; nothing is hex-compressed.
+    pub const NO_HEX_COMPRESSION: Self = Self {
+        hex_as_bytes: false,
+        ..Self::DEFAULT
+    };
+
+    /// Only store hex as bytes in the value of `e`, `p` and `a` tags, where
+    /// NIP-01 puts event ids and pubkeys.
+    pub fn references_only() -> Self {
+        let positions = ["e", "p", "a"].map(|name| (name.to_string(), 1));
+        Self {
+            hex_positions: Some(positions.to_vec()),
+            ..Self::DEFAULT
+        }
+    }
+
+    /// Whether `elem`, at `index` in a tag named `name`, is stored as bytes.
+    ///
+    /// This is the one place the element-kind rule lives; every encoder goes
+    /// through it. Empty strings are always text.
+    pub(crate) fn stores_as_bytes(&self, name: &str, index: usize, elem: &str) -> bool {
+        self.hex_as_bytes
+            && elem.len() / 2 >= self.min_hex_bytes.max(1)
+            && crate::is_lowercase_hex(elem)
+            && self
+                .hex_positions
+                .as_ref()
+                .is_none_or(|ps| ps.iter().any(|(n, i)| n == name && *i == index))
+    }
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{DecodeLimits, Error, Note, NoteParser, Tags};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// A packed note that owns its bytes.
+///
+/// The payload is fully validated once, in [`OwnedNote::new`], including
+/// every tag element, and the accessors then return views borrowed from the
+/// note itself. Unlike [`Note`], an `OwnedNote` can be stored in caches,
+/// returned from functions or sent across threads without keeping a separate
+/// buffer alive.
+///
+/// `B` is the byte storage: a `Vec<u8>` by default, or an `Arc<[u8]>` to make
+/// clones cheap (see [`OwnedNote::into_shared`]).
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, OwnedNote, pack_note};
+///
+/// fn load() -> OwnedNote {
+///     let note = NoteBuf {
+///         id: "aa".repeat(32),
+///         pubkey: "bb".repeat(32),
+///         sig: "cc".repeat(64),
+///         content: "hello".into(),
+///         tags: vec![vec!["t".into(), "nostr".into()]],
+///         ..NoteBuf::default()
+///     };
+///     OwnedNote::new(pack_note(&note).unwrap()).unwrap()
+/// }
+///
+/// let note = load().into_shared();
+/// let shared = note.clone();
+/// std::thread::spawn(move || assert_eq!(shared.content(), "hello"))
+///     .join()
+///     .unwrap();
+///
+/// assert_eq!(note.id(), &[0xaa; 32]);
+/// assert_eq!(note.tags().len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OwnedNote<B = Vec<u8>> {
+    data: B,
+    id: usize, // offset of the id; pubkey and sig follow it
+    created_at: u64,
+    kind: u64,
+    content: Range<usize>, // the tags block starts at `content.end`
+}
+
+impl<B: AsRef<[u8]>> OwnedNote<B> {
+    /// Validate `data` as a notepack payload and take ownership of it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`Error`] from [`NoteParser::into_note`], or from the first
+    /// malformed tag element.
+    pub fn new(data: B) -> Result<Self, Error> {
+        Self::with_limits(data, DecodeLimits::UNLIMITED)
+    }
+
+    /// Like [`OwnedNote::new`], but enforces `limits` while validating.
+    pub fn with_limits(data: B, limits: DecodeLimits) -> Result<Self, Error> {
+        let bytes = data.as_ref();
+        let note = NoteParser::with_limits(bytes, limits).into_note()?;
+
+        let mut tags = note.tags.clone();
+        while let Some(elems) = tags.next_tag()? {
+            for elem in elems {
+                elem?;
+            }
+        }
+
+        let offset_of = |part: &[u8]| part.as_ptr() as usize - bytes.as_ptr() as usize;
+        let content = offset_of(note.content.as_bytes());
+        Ok(Self {
+            id: offset_of(note.id),
+            created_at: note.created_at,
+            kind: note.kind,
+            content: content..content + note.content.len(),
+            data,
+        })
+    }
+
+    /// The zero-copy [`Note`] view of this note.
+    pub fn note(&self) -> Note<'_> {
+        Note {
+            id: self.id(),
+            pubkey: self.pubkey(),
+            sig: self.sig(),
+            content: self.content(),
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: self.tags(),
+        }
+    }
+
+    /// notepack format version the note was decoded from, see [`Note::version`]
+    pub fn version(&self) -> u8 {
+        self.note().version()
+    }
+
+    /// 32-bytes sha256 of the the serialized event data
+    pub fn id(&self) -> &[u8; 32] {
+        self.fixed(self.id)
+    }
+
+    /// 32-bytes public key of the event creator
+    pub fn pubkey(&self) -> &[u8; 32] {
+        self.fixed(self.id + 32)
+    }
+
+    /// 64-bytes signature of the id
+    pub fn sig(&self) -> &[u8; 64] {
+        self.fixed(self.id + 64)
+    }
+
+    /// unix timestamp in seconds
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    /// event kind
+    pub fn kind(&self) -> u64 {
+        self.kind
+    }
+
+    /// arbitrary string
+    pub fn content(&self) -> &str {
+        std::str::from_utf8(&self.as_bytes()[self.content.clone()]).expect("validated in new")
+    }
+
+    /// A lazy view over the tags, see [`Tags`].
+    ///
+    /// The tags were validated in [`OwnedNote::new`], so walking them does
+    /// not fail.
+    pub fn tags(&self) -> Tags<'_> {
+        let offset = self.content.end;
+        let mut input = &self.as_bytes()[offset..];
+        Tags::parse_at(&mut input, offset, &DecodeLimits::UNLIMITED, false)
+            .expect("validated in new")
+    }
+
+    /// The packed payload.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+
+    /// Give back the byte storage.
+    pub fn into_inner(self) -> B {
+        self.data
+    }
+
+    fn fixed<const N: usize>(&self, offset: usize) -> &[u8; N] {
+        self.as_bytes()[offset..offset + N]
+            .try_into()
+            .expect("validated in new")
+    }
+}
+
+impl OwnedNote<Vec<u8>> {
+    /// Move the bytes into an `Arc<[u8]>`, so clones share one allocation.
+    pub fn into_shared(self) -> OwnedNote<Arc<[u8]>> {
+        OwnedNote {
+            data: self.data.into(),
+            id: self.id,
+            created_at: self.created_at,
+            kind: self.kind,
+            content: self.content,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoteBuf, StringType, pack_note, test_util};
+
+    fn packed() -> Vec<u8> {
+        let note = NoteBuf {
+            created_at: 1_720_000_000,
+            kind: 30_023,
+            tags: vec![
+                vec!["e".into(), "aa".repeat(32), "wss://relay".into()],
+                vec![],
+            ],
+            content: "é".repeat(100),
+            ..test_util::note()
+        };
+        pack_note(&note).expect("pack")
+    }
+
+    #[test]
+    fn accessors_match_parsed_note() -> Result<(), Error> {
+        let bytes = packed();
+        let parsed = NoteParser::new(&bytes).into_note()?;
+        let owned = OwnedNote::new(bytes.clone())?.into_shared();
+
+        assert_eq!(owned.version(), parsed.version());
+        assert_eq!(owned.id(), parsed.id);
+        assert_eq!(owned.pubkey(), parsed.pubkey);
+        assert_eq!(owned.sig(), parsed.sig);
+        assert_eq!(owned.created_at(), parsed.created_at);
+        assert_eq!(owned.kind(), parsed.kind);
+        assert_eq!(owned.content(), parsed.content);
+        assert_eq!(owned.as_bytes(), bytes.as_slice());
+        assert_eq!(owned.note().to_packed()?, bytes);
+
+        let mut tags = owned.tags();
+        let mut elems = tags.next_tag()?.expect("tag");
+        assert!(matches!(elems.next(), Some(Ok(StringType::Str("e")))));
+        assert!(matches!(elems.next(), Some(Ok(StringType::Bytes(bs))) if bs == [0xaa; 32]));
+        drop(elems);
+        assert_eq!(tags.next_tag()?.expect("tag").remaining(), 0);
+        assert!(tags.next_tag()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn new_validates_every_tag_element() {
+        let mut bytes = packed();
+        let relay = bytes
+            .windows(11)
+            .position(|w| w == b"wss://relay")
+            .expect("relay");
+        bytes[relay] = 0xff;
+
+        // into_note is lazy about tags, OwnedNote is not
+        assert!(NoteParser::new(&bytes).into_note().is_ok());
+        let err = OwnedNote::new(bytes).unwrap_err();
+        assert!(matches!(err.root(), Error::Utf8(_)));
+
+        let bytes = packed();
+        let limits = DecodeLimits {
+            max_tags: 1,
+            ..DecodeLimits::UNLIMITED
+        };
+        let err = OwnedNote::with_limits(&bytes[..], limits).unwrap_err();
+        assert!(matches!(err.root(), Error::TooManyTags));
+    }
+}
@@ -1,7 +1,14 @@
-use crate::{Note, Tags};
-use crate::error::Error;
+use crate::error::{DecodeField, Error};
+use crate::limits::DecodeLimits;
 use crate::stringtype::StringType;
-use crate::varint::{read_tagged_varint, read_varint};
+use crate::varint::{read_canonical_varint, read_varint};
+use crate::{Note, Tags};
+
+/// The format version written by [`pack_note`](crate::pack_note).
+///
+/// Decoders dispatch on the leading version varint and reject versions they
+/// do not know with [`Error::UnsupportedVersion`]. See `SPEC.md` §13.
+pub const NOTEPACK_VERSION: u8 = 1;
 
 /// Represents a parsed field from a notepack‐encoded Nostr note.
 ///
@@ -10,7 +17,7 @@ use crate::varint::{read_tagged_varint, read_varint};
 /// through the byte stream.
 #[derive(Debug, Clone)]
 pub enum ParsedField<'a> {
-    /// Format version (currently always `1`).
+    /// Format version (currently always [`NOTEPACK_VERSION`](crate::NOTEPACK_VERSION)).
     Version(u8),
 
     /// 32‑byte event ID (SHA‑256 of serialized event).
@@ -46,6 +53,13 @@ pub enum ParsedField<'a> {
 /// Yields [`ParsedField`] items in the order they appear in the binary format.
 /// Errors are non‑recoverable: once an error is yielded, the parser halts.
 ///
+/// By default the parser accepts any payload it can make sense of; use
+/// [`NoteParser::strict`] to also require the canonical encoding.
+///
+/// Decode errors are wrapped in [`Error::At`] with the byte offset and
+/// [`DecodeField`] being parsed, e.g. `tag 12 element 3 at offset 4810:
+/// notepack string is truncated`. Use [`Error::root`] to match on the cause.
+///
 /// Implements [`Iterator`], so you can do:
 ///
 /// ```rust
@@ -70,6 +84,24 @@ pub struct NoteParser<'a> {
 
     /// Number of elements remaining in the current tag.
     elems_remaining: u64,
+
+    /// Bounds enforced while parsing.
+    limits: DecodeLimits,
+
+    /// Bytes consumed since the start of the payload.
+    offset: usize,
+
+    /// Number of tags whose element count has been read.
+    tags_read: u64,
+
+    /// Number of elements read from the current tag.
+    elems_read: u64,
+
+    /// Layout selected by the version varint.
+    layout: Layout,
+
+    /// Reject anything but the canonical encoding.
+    strict: bool,
 }
 
 /// Internal parser state machine.
@@ -101,14 +133,59 @@ impl ParserState {
 impl<'a> NoteParser<'a> {
     /// Create a new [`NoteParser`] over a binary notepack slice.
     pub fn new(data: &'a [u8]) -> Self {
+        Self::with_limits(data, DecodeLimits::UNLIMITED)
+    }
+
+    /// Create a new [`NoteParser`] that enforces `limits`.
+    ///
+    /// Limits carry over to the [`Tags`] returned by [`NoteParser::into_note`].
+    pub fn with_limits(data: &'a [u8], limits: DecodeLimits) -> Self {
         Self {
             data,
             state: ParserState::Start,
             tags_remaining: 0,
             elems_remaining: 0,
+            limits,
+            offset: 0,
+            tags_read: 0,
+            elems_read: 0,
+            layout: Layout::V1,
+            strict: false,
         }
     }
 
+    /// Only accept the canonical encoding of a note, so that every note has
+    /// exactly one valid byte form and raw payloads can be hashed or deduped.
+    ///
+    /// In strict mode:
+    /// - varints must use their shortest form ([`Error::NonCanonicalVarint`]),
+    /// - [`StringType::Bytes`] tag elements must not be empty ([`Error::EmptyBytes`]),
+    /// - no bytes may follow the last tag ([`Error::TrailingBytes`]).
+    ///
+    /// [`NoteParser::into_note`] walks the whole tags block up front to check
+    /// this, instead of leaving it to the lazy [`Tags`] cursor.
+    ///
+    /// ```rust
+    /// # use notepack::{Error, NoteBuf, NoteParser, pack_note};
+    /// let note = NoteBuf {
+    ///     id: "aa".repeat(32),
+    ///     pubkey: "bb".repeat(32),
+    ///     sig: "cc".repeat(64),
+    ///     ..NoteBuf::default()
+    /// };
+    /// let mut packed = pack_note(&note).unwrap();
+    /// assert!(NoteParser::new(&packed).strict().into_note().is_ok());
+    ///
+    /// packed.push(0);
+    /// assert!(NoteParser::new(&packed).into_note().is_ok());
+    /// let err = NoteParser::new(&packed).strict().into_note().unwrap_err();
+    /// assert!(matches!(err.root(), Error::TrailingBytes));
+    /// ```
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
     /// Parse a fully-borrowed `Note<'a>` from the current cursor.
     ///
     /// This is zero-copy for id/pubkey/sig/content; `tags` is returned as a lazy
@@ -118,30 +195,73 @@ impl<'a> NoteParser<'a> {
     /// Typical use:
     /// ```
     /// use notepack::NoteParser;
-    /// let bytes = NoteParser::decode("notepack_737yskaxtaKQSL3IPPhOOR8T1R4G/f4ARPHGeNPfOpF4417q9YtU+4JZGOD3+Y0S3uVU6/edo64oTqJQ0pOF29Ms7GmX6fzM4Wjc6sohGPlbdRGLjhuqIRccETX5DliwUFy9qGg2lDD9oMl8ijoNFq4wwJ5Ikmr4Vh7NYWBwOkuo/anEBgECaGkA").expect("ok");
+    /// let bytes = NoteParser::decode("notepack_AfHnvCqXVkU/zA6A7PYhg/qVuaEnigEoHbwxC2d3Mg6Af+Q321iE7gE/cBp1+NGoTstDTpl/KjFBFoVVH//xuEF1UH+E14IRpo8vlkIh9Vh6qVembBlB0BElyqB7mqvfWpjD5j0f4eMHy/AbdLChuV/+Y262dGwAFn4NSOWxEDLVlsmpxAYBAmhpAA").expect("ok");
     /// let note = NoteParser::new(&bytes).into_note().expect("ok");
     /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedVersion`] if the payload was written in a
+    /// layout this crate does not know about.
     pub fn into_note(mut self) -> Result<Note<'a>, Error> {
-        // version (currently not stored)
-        let _version = read_varint(&mut self.data)? as u8;
+        check_limit(
+            self.data.len() as u64,
+            self.limits.max_total_size,
+            Error::NoteTooLarge,
+        )?;
+
+        let before = self.data.len();
+        let (_version, layout) = read_version(&mut self.data, self.strict)
+            .map_err(|e| e.at(self.offset, DecodeField::Version))?;
+        self.offset += before - self.data.len();
+
+        match layout {
+            Layout::V1 => self.into_note_v1(),
+        }
+    }
+
+    /// Parse the rest of a version 1 note, positioned just after the version.
+    fn into_note_v1(mut self) -> Result<Note<'a>, Error> {
+        let end = self.offset + self.data.len();
+
+        // read one field, annotating errors with where it started
+        macro_rules! field {
+            ($field:expr, $expr:expr) => {{
+                let at = end - self.data.len();
+                $expr.map_err(|e: Error| e.at(at, $field))?
+            }};
+        }
 
         // fixed-size fields
-        let id = read_bytes(32, &mut self.data)?;
-        let pubkey = read_bytes(32, &mut self.data)?;
-        let sig = read_bytes(64, &mut self.data)?;
+        let id = field!(DecodeField::Id, read_bytes(32, &mut self.data));
+        let pubkey = field!(DecodeField::Pubkey, read_bytes(32, &mut self.data));
+        let sig = field!(DecodeField::Sig, read_bytes(64, &mut self.data));
 
         // integers
-        let created_at = read_varint(&mut self.data)?;
-        let kind = read_varint(&mut self.data)?;
+        let created_at = field!(
+            DecodeField::CreatedAt,
+            read_uint(&mut self.data, self.strict)
+        );
+        let kind = field!(DecodeField::Kind, read_uint(&mut self.data, self.strict));
 
         // content
-        let content_len = read_varint(&mut self.data)?;
-        let content_bytes = read_bytes(content_len, &mut self.data)?;
-        let content = std::str::from_utf8(content_bytes)?;
+        let content = field!(
+            DecodeField::Content,
+            read_content(&mut self.data, &self.limits, self.strict)
+        );
 
         // tags: create a lazy cursor positioned at the tags block
         let mut tags_cursor = self.data;
-        let tags = Tags::parse(&mut tags_cursor)?; // leaves tags_cursor on first tag's elems
+        // leaves tags_cursor on first tag's elems
+        let tags = Tags::parse_at(
+            &mut tags_cursor,
+            end - self.data.len(),
+            &self.limits,
+            self.strict,
+        )?;
+        if self.strict {
+            tags.clone().validate_to_end()?;
+        }
 
         // Safely coerce slices to fixed-size array refs;
         // These `try_into()` must succeed because we just read exact lengths above.
@@ -176,6 +296,72 @@ impl<'a> NoteParser<'a> {
     pub fn current_state(&self) -> ParserState {
         self.state
     }
+
+    /// Byte offset of the next field, counted from the start of the payload.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The field that the next call to [`Iterator::next`] will parse.
+    pub(crate) fn next_field(&self) -> DecodeField {
+        use ParserState::*;
+
+        match self.state {
+            Start | Errored => DecodeField::Version,
+            AfterVersion => DecodeField::Id,
+            AfterId => DecodeField::Pubkey,
+            AfterPubkey => DecodeField::Sig,
+            AfterSig => DecodeField::CreatedAt,
+            AfterCreatedAt => DecodeField::Kind,
+            AfterKind => DecodeField::Content,
+            AfterContent => DecodeField::NumTags,
+            Done => DecodeField::End,
+            ReadingTags if self.elems_remaining == 0 && self.tags_remaining == 0 => {
+                DecodeField::End
+            }
+            ReadingTags if self.elems_remaining == 0 => DecodeField::NumTagElems {
+                tag: self.tags_read,
+            },
+            ReadingTags => DecodeField::TagElem {
+                tag: self.tags_read.saturating_sub(1),
+                elem: self.elems_read,
+            },
+        }
+    }
+
+    /// Save the parser's position without borrowing its input.
+    pub(crate) fn detach(&self) -> NoteParser<'static> {
+        NoteParser {
+            data: &[],
+            state: self.state,
+            tags_remaining: self.tags_remaining,
+            elems_remaining: self.elems_remaining,
+            limits: self.limits,
+            offset: self.offset,
+            tags_read: self.tags_read,
+            elems_read: self.elems_read,
+            layout: self.layout,
+            strict: self.strict,
+        }
+    }
+
+    /// Resume a detached parser over `data`, the bytes starting at its offset.
+    pub(crate) fn reattach<'b>(&self, data: &'b [u8]) -> NoteParser<'b> {
+        NoteParser {
+            data,
+            ..self.detach()
+        }
+    }
+
+    /// Halt the parser, e.g. after a caller-side check failed.
+    pub(crate) fn halt(&mut self) {
+        self.state = ParserState::Errored;
+    }
+
+    /// Tags and elements left to read, as `(tags_remaining, elems_remaining)`.
+    pub(crate) fn tag_counts(&self) -> (u64, u64) {
+        (self.tags_remaining, self.elems_remaining)
+    }
 }
 
 /// Base64 decode using the RFC 4648 alphabet **without padding** (`=`).
@@ -192,90 +378,157 @@ impl<'a> Iterator for NoteParser<'a> {
     ///
     /// Returns `None` when parsing is complete or after an unrecoverable error.
     fn next(&mut self) -> Option<Self::Item> {
-        use ParserState::*;
+        if self.state == ParserState::Done
+            && let Err(e) = self.check_end()
+        {
+            self.state = ParserState::Errored;
+            return Some(Err(e.at(self.offset, DecodeField::End)));
+        }
 
         if self.state.is_halted() {
             return None;
         }
 
-        // small helper to make error propagation less noisy
-        macro_rules! read_or_err {
-            ($expr:expr) => {
-                match $expr {
-                    Ok(val) => val,
-                    Err(e) => {
-                        self.state = Errored;
-                        return Some(Err(e));
-                    }
-                }
-            };
+        if self.state == ParserState::Start
+            && let Err(e) = check_limit(
+                self.data.len() as u64,
+                self.limits.max_total_size,
+                Error::NoteTooLarge,
+            )
+        {
+            // a whole-payload check, so there is no field to point at
+            self.state = ParserState::Errored;
+            return Some(Err(e));
         }
 
-        let item = match self.state {
-            Start => {
-                let version = read_or_err!(read_varint(&mut self.data)) as u8;
-                self.state = AfterVersion;
-                Ok(ParsedField::Version(version))
+        let field = self.next_field();
+        let before = self.data.len();
+
+        let result = match self.state {
+            ParserState::Start => self.next_version(),
+            _ => match self.layout {
+                Layout::V1 => self.next_v1(),
+            },
+        };
+
+        match result {
+            Ok(Some(item)) => {
+                self.offset += before - self.data.len();
+                Some(Ok(item))
             }
+            Ok(None) => None,
+            Err(e) => {
+                self.state = ParserState::Errored;
+                Some(Err(e.at(self.offset, field)))
+            }
+        }
+    }
+}
+
+impl<'a> NoteParser<'a> {
+    /// Read the version varint and select the layout for the rest of the note.
+    fn next_version(&mut self) -> Result<Option<ParsedField<'a>>, Error> {
+        let (version, layout) = read_version(&mut self.data, self.strict)?;
+        self.layout = layout;
+        self.state = ParserState::AfterVersion;
+        Ok(Some(ParsedField::Version(version)))
+    }
+
+    /// Parse the next field of a version 1 note (SPEC §3.1).
+    fn next_v1(&mut self) -> Result<Option<ParsedField<'a>>, Error> {
+        use ParserState::*;
+
+        let item = match self.state {
             AfterVersion => {
-                let id = read_or_err!(read_bytes(32, &mut self.data));
+                let id = read_bytes(32, &mut self.data)?;
                 self.state = AfterId;
-                Ok(ParsedField::Id(id))
+                ParsedField::Id(id)
             }
             AfterId => {
-                let pk = read_or_err!(read_bytes(32, &mut self.data));
+                let pk = read_bytes(32, &mut self.data)?;
                 self.state = AfterPubkey;
-                Ok(ParsedField::Pubkey(pk))
+                ParsedField::Pubkey(pk)
             }
             AfterPubkey => {
-                let sig = read_or_err!(read_bytes(64, &mut self.data));
+                let sig = read_bytes(64, &mut self.data)?;
                 self.state = AfterSig;
-                Ok(ParsedField::Sig(sig))
+                ParsedField::Sig(sig)
             }
             AfterSig => {
-                let ts = read_or_err!(read_varint(&mut self.data));
+                let ts = read_uint(&mut self.data, self.strict)?;
                 self.state = AfterCreatedAt;
-                Ok(ParsedField::CreatedAt(ts))
+                ParsedField::CreatedAt(ts)
             }
             AfterCreatedAt => {
-                let kind = read_or_err!(read_varint(&mut self.data));
+                let kind = read_uint(&mut self.data, self.strict)?;
                 self.state = AfterKind;
-                Ok(ParsedField::Kind(kind))
+                ParsedField::Kind(kind)
             }
             AfterKind => {
-                let content_len = read_or_err!(read_varint(&mut self.data));
-                let bytes = read_or_err!(read_bytes(content_len, &mut self.data));
-                let s = read_or_err!(std::str::from_utf8(bytes).map_err(Error::Utf8));
+                let s = read_content(&mut self.data, &self.limits, self.strict)?;
                 self.state = AfterContent;
-                Ok(ParsedField::Content(s))
+                ParsedField::Content(s)
             }
             AfterContent => {
-                let num_tags = read_or_err!(read_varint(&mut self.data));
+                let num_tags = read_uint(&mut self.data, self.strict)?;
+                check_limit(num_tags, self.limits.max_tags, Error::TooManyTags)?;
                 self.tags_remaining = num_tags;
                 self.state = if num_tags > 0 { ReadingTags } else { Done };
-                Ok(ParsedField::NumTags(num_tags))
+                ParsedField::NumTags(num_tags)
             }
             ReadingTags => {
                 if self.elems_remaining == 0 {
                     if self.tags_remaining == 0 {
                         self.state = Done;
-                        return None;
+                        return self.check_end().map(|()| None);
                     }
-                    let num_elems = read_or_err!(read_varint(&mut self.data));
+                    let num_elems = read_uint(&mut self.data, self.strict)?;
+                    check_limit(
+                        num_elems,
+                        self.limits.max_elems_per_tag,
+                        Error::TooManyTagElems,
+                    )?;
                     self.elems_remaining = num_elems;
                     self.tags_remaining -= 1;
-                    return Some(Ok(ParsedField::NumTagElems(num_elems)));
+                    self.tags_read += 1;
+                    self.elems_read = 0;
+                    ParsedField::NumTagElems(num_elems)
+                } else {
+                    let tag = read_string(&mut self.data, self.limits.max_elem_len, self.strict)?;
+                    self.elems_remaining -= 1;
+                    self.elems_read += 1;
+                    ParsedField::Tag(tag)
                 }
-
-                let tag = read_or_err!(read_string(&mut self.data));
-                self.elems_remaining -= 1;
-                Ok(ParsedField::Tag(tag))
             }
-            Done => return None,
-            Errored => return None,
+            Start | Done | Errored => return Ok(None),
         };
 
-        Some(item)
+        Ok(Some(item))
+    }
+
+    /// In strict mode, check that nothing follows the note.
+    fn check_end(&self) -> Result<(), Error> {
+        if self.strict && !self.data.is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(())
+    }
+}
+
+/// Binary layouts this crate can decode, selected by the leading version varint.
+///
+/// New layouts get their own variant and `next_*` / `into_note_*` functions so
+/// they can be decoded side by side with older ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    V1,
+}
+
+/// Read the version varint, rejecting versions this crate cannot decode.
+fn read_version(input: &mut &[u8], strict: bool) -> Result<(u8, Layout), Error> {
+    match read_uint(input, strict)? {
+        1 => Ok((1, Layout::V1)),
+        v => Err(Error::UnsupportedVersion(v)),
     }
 }
 
@@ -283,23 +536,75 @@ impl<'a> Iterator for NoteParser<'a> {
 ///
 /// Returns [`Error::Truncated`] if fewer than `len` bytes remain.
 fn read_bytes<'a>(len: u64, input: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    if (input.len() as u64) < len {
+        return Err(Error::Truncated);
+    }
     let (head, tail) = input.split_at(len as usize);
     *input = tail;
     Ok(head)
 }
 
+/// Read the length-prefixed UTF-8 content, enforcing `max_content_bytes`.
+fn read_content<'a>(
+    input: &mut &'a [u8],
+    limits: &DecodeLimits,
+    strict: bool,
+) -> Result<&'a str, Error> {
+    let len = read_uint(input, strict)?;
+    check_limit(len, limits.max_content_bytes, Error::ContentTooLarge)?;
+    let bytes = read_bytes(len, input)?;
+    Ok(std::str::from_utf8(bytes)?)
+}
+
+/// Read a varint, requiring its shortest form if `strict`.
+pub(crate) fn read_uint(input: &mut &[u8], strict: bool) -> Result<u64, Error> {
+    if strict {
+        read_canonical_varint(input)
+    } else {
+        read_varint(input)
+    }
+}
+
+/// Read a tag element's tagged varint, returning `(len, is_bytes)`.
+///
+/// Lengths over `max_len` fail with [`Error::TagElemTooLarge`], and lengths
+/// past the end of `input` with [`Error::Truncated`], so the payload can be
+/// split off directly. In strict mode empty Bytes fail with [`Error::EmptyBytes`].
+pub(crate) fn read_elem_header(
+    input: &mut &[u8],
+    max_len: u64,
+    strict: bool,
+) -> Result<(usize, bool), Error> {
+    let raw = read_uint(input, strict)?;
+    let (len, is_bytes) = (raw >> 1, raw & 1 != 0);
+    check_limit(len, max_len, Error::TagElemTooLarge)?;
+    if (input.len() as u64) < len {
+        return Err(Error::Truncated);
+    }
+    if strict && is_bytes && len == 0 {
+        return Err(Error::EmptyBytes);
+    }
+    Ok((len as usize, is_bytes))
+}
+
+/// Return `err` if `value` exceeds `max`.
+pub(crate) fn check_limit(value: u64, max: u64, err: Error) -> Result<(), Error> {
+    if value > max { Err(err) } else { Ok(()) }
+}
+
 /// Read a tagged string (see §3.2 of spec) from the input.
 ///
-/// Uses [`read_tagged_varint`] to determine payload length and type.
+/// Uses [`read_elem_header`] to determine payload length and type.
 /// Returns:
 ///  * [`StringType::Str`] if `is_bytes == false`
 ///  * [`StringType::Bytes`] if `is_bytes == true`
-pub(crate) fn read_string<'a>(input: &mut &'a [u8]) -> Result<StringType<'a>, Error> {
-    let (len, is_bytes) = read_tagged_varint(input)?;
-    if input.len() < len as usize {
-        return Err(Error::Truncated);
-    }
-    let (head, tail) = input.split_at(len as usize);
+pub(crate) fn read_string<'a>(
+    input: &mut &'a [u8],
+    max_len: u64,
+    strict: bool,
+) -> Result<StringType<'a>, Error> {
+    let (len, is_bytes) = read_elem_header(input, max_len, strict)?;
+    let (head, tail) = input.split_at(len);
     *input = tail;
 
     Ok(if is_bytes {
@@ -313,6 +618,7 @@ pub(crate) fn read_string<'a>(input: &mut &'a [u8]) -> Result<StringType<'a>, Er
 #[cfg(test)]
 mod into_note_tests {
     use super::*;
+    use crate::DecodeLimits;
     use crate::stringtype::StringType;
     use crate::varint::{write_tagged_varint, write_varint};
 
@@ -512,6 +818,321 @@ mod into_note_tests {
         let mut tags = note.tags.clone();
         let mut t0 = tags.next_tag().expect("ok").expect("tag0");
         let err = t0.next().unwrap().unwrap_err();
-        matches!(err, Error::Truncated);
+        assert!(matches!(err.root(), Error::Truncated));
+    }
+
+    #[test]
+    fn into_note_reports_truncated_fixed_fields_instead_of_panicking() {
+        let bytes = build_note_bytes([0x01; 32], [0x02; 32], [0x03; 64], 1, 1, "hi", &[]);
+
+        for len in [0, 1, 20, 33, 100, 129, bytes.len() - 2] {
+            let err = NoteParser::new(&bytes[..len]).into_note().unwrap_err();
+            assert!(
+                matches!(err.root(), Error::Truncated | Error::VarintUnterminated),
+                "len {len}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn limits_are_enforced_by_parser_and_lazy_tags() -> Result<(), Error> {
+        let bytes = build_note_bytes(
+            [0x01; 32],
+            [0x02; 32],
+            [0x03; 64],
+            1,
+            1,
+            "hello",
+            &[
+                &[TagElem::S("p"), TagElem::B(&[0xaa; 32])],
+                &[TagElem::S("t"), TagElem::S("nostr"), TagElem::S("x")],
+            ],
+        );
+
+        let first_error =
+            |limits: DecodeLimits| NoteParser::with_limits(&bytes, limits).find_map(Result::err);
+        let limits = DecodeLimits::default();
+
+        assert!(first_error(limits).is_none());
+        assert!(matches!(
+            first_error(DecodeLimits {
+                max_total_size: 100,
+                ..limits
+            })
+            .as_ref()
+            .map(Error::root),
+            Some(Error::NoteTooLarge)
+        ));
+        assert!(matches!(
+            first_error(DecodeLimits {
+                max_content_bytes: 4,
+                ..limits
+            })
+            .as_ref()
+            .map(Error::root),
+            Some(Error::ContentTooLarge)
+        ));
+        assert!(matches!(
+            first_error(DecodeLimits {
+                max_tags: 1,
+                ..limits
+            })
+            .as_ref()
+            .map(Error::root),
+            Some(Error::TooManyTags)
+        ));
+        assert!(matches!(
+            first_error(DecodeLimits {
+                max_elems_per_tag: 2,
+                ..limits
+            })
+            .as_ref()
+            .map(Error::root),
+            Some(Error::TooManyTagElems)
+        ));
+        assert!(matches!(
+            first_error(DecodeLimits {
+                max_elem_len: 16,
+                ..limits
+            })
+            .as_ref()
+            .map(Error::root),
+            Some(Error::TagElemTooLarge)
+        ));
+
+        // into_note checks the eager fields up front...
+        let err = NoteParser::with_limits(
+            &bytes,
+            DecodeLimits {
+                max_tags: 1,
+                ..limits
+            },
+        )
+        .into_note()
+        .unwrap_err();
+        assert!(matches!(err.root(), Error::TooManyTags));
+
+        // ...and hands the rest to the lazy tag cursor
+        let note = NoteParser::with_limits(
+            &bytes,
+            DecodeLimits {
+                max_elems_per_tag: 2,
+                max_elem_len: 16,
+                ..limits
+            },
+        )
+        .into_note()?;
+        let mut tags = note.tags.clone();
+        {
+            let mut t0 = tags.next_tag()?.expect("tag0");
+            assert!(t0.next().expect("p").is_ok());
+            let err = t0.next().expect("oversized").unwrap_err();
+            assert!(matches!(err.root(), Error::TagElemTooLarge));
+        }
+        let mut tags = note.tags.clone();
+        {
+            let t0 = tags.next_tag()?.expect("tag0");
+            assert!(matches!(
+                t0.finish().unwrap_err().root(),
+                Error::TagElemTooLarge
+            ));
+        }
+        let mut tags = note.tags.clone();
+        tags.next_tag()?.expect("tag0").next();
+        // dropping tag0 early cannot skip its oversized element, so the cursor stays put
+        assert!(tags.next_tag().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn limits_reject_too_many_elems_in_lazy_tags() -> Result<(), Error> {
+        let bytes = build_note_bytes(
+            [0x01; 32],
+            [0x02; 32],
+            [0x03; 64],
+            1,
+            1,
+            "",
+            &[&[TagElem::S("t"), TagElem::S("a"), TagElem::S("b")]],
+        );
+        let limits = DecodeLimits {
+            max_elems_per_tag: 2,
+            ..DecodeLimits::default()
+        };
+        let note = NoteParser::with_limits(&bytes, limits).into_note()?;
+        let mut tags = note.tags.clone();
+        assert!(matches!(
+            tags.next_tag().unwrap_err().root(),
+            Error::TooManyTagElems
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() -> Result<(), Error> {
+        let mut bytes = build_note_bytes([0x01; 32], [0x02; 32], [0x03; 64], 1, 1, "hi", &[]);
+        assert_eq!(
+            NoteParser::new(&bytes).into_note()?.version(),
+            NOTEPACK_VERSION
+        );
+
+        for version in [0u64, 2, 300] {
+            let mut other = Vec::new();
+            write_varint(&mut other, version);
+            other.extend_from_slice(&bytes[1..]);
+
+            let err = NoteParser::new(&other).into_note().unwrap_err();
+            assert!(matches!(err.root(), Error::UnsupportedVersion(v) if *v == version));
+            assert_eq!(err.field(), Some(DecodeField::Version));
+
+            let mut parser = NoteParser::new(&other);
+            let err = parser.next().expect("version").unwrap_err();
+            assert!(matches!(err.root(), Error::UnsupportedVersion(v) if *v == version));
+            assert!(parser.next().is_none());
+        }
+
+        bytes[0] = 2;
+        assert_eq!(
+            NoteParser::new(&bytes).into_note().unwrap_err().to_string(),
+            "version at offset 0: unsupported notepack version 2"
+        );
+        Ok(())
+    }
+
+    fn strict_error(bytes: &[u8]) -> Option<Error> {
+        let iter_err = NoteParser::new(bytes).strict().find_map(Result::err);
+        let note_err = NoteParser::new(bytes).strict().into_note().err();
+        assert_eq!(
+            iter_err.as_ref().map(Error::to_string),
+            note_err.as_ref().map(Error::to_string)
+        );
+        iter_err
+    }
+
+    #[test]
+    fn strict_mode_rejects_non_canonical_encodings() {
+        let canonical = build_note_bytes(
+            [0x01; 32],
+            [0x02; 32],
+            [0x03; 64],
+            1,
+            1,
+            "hi",
+            &[&[TagElem::S("e"), TagElem::B(&[0xaa; 32])]],
+        );
+        assert!(strict_error(&canonical).is_none());
+
+        // created_at = 1 encoded as 0x81 0x00
+        let mut overlong = canonical[..129].to_vec();
+        overlong.extend_from_slice(&[0x81, 0x00]);
+        overlong.extend_from_slice(&canonical[130..]);
+        assert!(NoteParser::new(&overlong).into_note().is_ok());
+        let err = strict_error(&overlong).expect("overlong");
+        assert!(matches!(err.root(), Error::NonCanonicalVarint));
+        assert_eq!(err.field(), Some(DecodeField::CreatedAt));
+
+        let mut trailing = canonical.clone();
+        trailing.push(0);
+        assert!(NoteParser::new(&trailing).all(|f| f.is_ok()));
+        let err = strict_error(&trailing).expect("trailing");
+        assert!(matches!(err.root(), Error::TrailingBytes));
+        assert_eq!(err.field(), Some(DecodeField::End));
+        assert_eq!(err.offset(), Some(canonical.len()));
+
+        let mut no_tags = build_note_bytes([0x01; 32], [0x02; 32], [0x03; 64], 1, 1, "", &[]);
+        assert!(strict_error(&no_tags).is_none());
+        no_tags.push(0);
+        let err = strict_error(&no_tags).expect("trailing after empty tags");
+        assert!(matches!(err.root(), Error::TrailingBytes));
+
+        let empty_bytes = build_note_bytes(
+            [0x01; 32],
+            [0x02; 32],
+            [0x03; 64],
+            1,
+            1,
+            "",
+            &[&[TagElem::S("e"), TagElem::B(&[])]],
+        );
+        assert!(NoteParser::new(&empty_bytes).into_note().is_ok());
+        let err = strict_error(&empty_bytes).expect("empty bytes");
+        assert!(matches!(err.root(), Error::EmptyBytes));
+        assert_eq!(err.field(), Some(DecodeField::TagElem { tag: 0, elem: 1 }));
+    }
+
+    #[test]
+    fn canonical_varint_rejects_overlong_and_overflowing_encodings() {
+        use crate::varint::read_canonical_varint;
+
+        let read = |mut bs: &[u8]| read_canonical_varint(&mut bs);
+        assert!(matches!(read(&[0x00]), Ok(0)));
+        assert!(matches!(read(&[0x80, 0x01]), Ok(128)));
+        assert!(matches!(
+            read(&[0x80, 0x00]),
+            Err(Error::NonCanonicalVarint)
+        ));
+        assert!(matches!(
+            read(&[0xff, 0x80, 0x00]),
+            Err(Error::NonCanonicalVarint)
+        ));
+
+        let mut max = [0xff; 10];
+        max[9] = 0x01;
+        assert!(matches!(read(&max), Ok(u64::MAX)));
+        max[9] = 0x02;
+        assert!(matches!(read(&max), Err(Error::VarintOverflow)));
+    }
+
+    #[test]
+    fn errors_carry_offset_and_field() -> Result<(), Error> {
+        let bytes = build_note_bytes(
+            [0x01; 32],
+            [0x02; 32],
+            [0x03; 64],
+            1,
+            1,
+            "hi",
+            &[
+                &[TagElem::S("p"), TagElem::B(&[0xaa; 32])],
+                &[TagElem::S("t"), TagElem::S("nostr")],
+            ],
+        );
+        // header is 1 + 32 + 32 + 64 + 1 + 1 + 3 bytes, tags start at 134
+        let mut parser = NoteParser::new(&bytes);
+        for _ in 0..7 {
+            parser.next().expect("field")?;
+        }
+        assert_eq!(parser.offset(), 134);
+
+        // cut off inside tag 1's second element
+        let cut = bytes.len() - 2;
+        let err = NoteParser::new(&bytes[..cut])
+            .find_map(Result::err)
+            .expect("truncated");
+        assert!(matches!(err.root(), Error::Truncated));
+        assert_eq!(err.field(), Some(DecodeField::TagElem { tag: 1, elem: 1 }));
+        assert_eq!(err.offset(), Some(bytes.len() - 6));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "tag 1 element 1 at offset {}: notepack string is truncated",
+                bytes.len() - 6
+            )
+        );
+
+        // the lazy cursor from into_note reports the same position
+        let note = NoteParser::new(&bytes[..cut]).into_note()?;
+        let mut tags = note.tags.clone();
+        tags.next_tag()?.expect("tag0").finish()?;
+        let err = tags.next_tag()?.expect("tag1").finish().unwrap_err();
+        assert_eq!(err.field(), Some(DecodeField::TagElem { tag: 1, elem: 1 }));
+        assert_eq!(err.offset(), Some(bytes.len() - 6));
+
+        let err = NoteParser::new(&bytes[..50]).into_note().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "pubkey at offset 33: notepack string is truncated"
+        );
+        Ok(())
     }
 }
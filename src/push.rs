@@ -0,0 +1,329 @@
+use crate::varint::read_varint;
+use crate::{DecodeLimits, Error, NoteParser, ParsedField, ParserState};
+
+/// A resumable notepack parser that is fed input in arbitrary chunks.
+///
+/// Unlike [`NoteParser`], which needs the whole payload up front, a
+/// `PushParser` buffers only what it has not parsed yet and emits each
+/// [`ParsedField`] as soon as its bytes are complete. Running out of input in
+/// the middle of a varint or field simply means "push more", so callers can
+/// early-reject a note (e.g. by id or pubkey) while the rest is still in flight.
+///
+/// Returned fields borrow from the parser's buffer and must be dropped before
+/// the next call to [`PushParser::push`] or [`PushParser::next_field`].
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, ParsedField, PushParser, pack_note};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     content: "hello".into(),
+///     ..NoteBuf::default()
+/// };
+/// let packed = pack_note(&note).unwrap();
+///
+/// let mut parser = PushParser::new();
+/// let mut content = None;
+/// for chunk in packed.chunks(7) {
+///     parser.push(chunk);
+///     while let Some(field) = parser.next_field().unwrap() {
+///         if let ParsedField::Content(c) = field {
+///             content = Some(c.to_string());
+///         }
+///     }
+/// }
+/// parser.finish().unwrap();
+/// assert_eq!(content.as_deref(), Some("hello"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PushParser {
+    buf: Vec<u8>,
+    pos: usize,
+    need: usize,
+    limits: DecodeLimits,
+    parser: NoteParser<'static>,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PushParser {
+    /// Create a parser positioned at the start of a note.
+    pub fn new() -> Self {
+        Self::with_limits(DecodeLimits::UNLIMITED)
+    }
+
+    /// Create a parser that enforces `limits`.
+    ///
+    /// `max_total_size` is checked against the bytes parsed so far plus the
+    /// bytes the next field is known to need, so oversized notes are rejected
+    /// before they are fully buffered.
+    pub fn with_limits(limits: DecodeLimits) -> Self {
+        // total size is tracked here, since the buffer may not hold the whole note
+        let parser_limits = DecodeLimits {
+            max_total_size: u64::MAX,
+            ..limits
+        };
+        Self {
+            buf: Vec::new(),
+            pos: 0,
+            need: 1,
+            limits,
+            parser: NoteParser::with_limits(&[], parser_limits),
+        }
+    }
+
+    /// Append a chunk of input.
+    pub fn push(&mut self, chunk: &[u8]) {
+        // drop what has already been handed out before growing the buffer
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Parse the next complete field.
+    ///
+    /// Returns `Ok(None)` when more input is needed or when the note is
+    /// finished; use [`PushParser::is_done`] to tell the two apart.
+    ///
+    /// # Errors
+    ///
+    /// Malformed input (varint overflow, invalid UTF-8) is reported as soon
+    /// as it is seen and halts the parser. Truncation is never an error here;
+    /// see [`PushParser::finish`]. Errors carry the offset and field, as with
+    /// [`NoteParser`].
+    pub fn next_field(&mut self) -> Result<Option<ParsedField<'_>>, Error> {
+        if self.current_state() == ParserState::Errored || self.is_done() {
+            return Ok(None);
+        }
+
+        let input = &self.buf[self.pos..];
+        if input.len() < self.need {
+            return Ok(None);
+        }
+
+        let mut parser = self.parser.reattach(input);
+        match parser.next() {
+            Some(Ok(field)) => {
+                if parser.offset() as u64 > self.limits.max_total_size {
+                    self.parser.halt();
+                    return Err(Error::NoteTooLarge);
+                }
+                self.pos += parser.offset() - self.parser.offset();
+                self.parser = parser.detach();
+                self.need = 1;
+                Ok(Some(field))
+            }
+            Some(Err(e)) if matches!(e.root(), Error::Truncated | Error::VarintUnterminated) => {
+                let (_, elems_remaining) = self.parser.tag_counts();
+                self.need = min_needed(self.current_state(), elems_remaining, input);
+                if self.parser.offset() as u64 + self.need as u64 > self.limits.max_total_size {
+                    self.parser.halt();
+                    return Err(Error::NoteTooLarge);
+                }
+                Ok(None)
+            }
+            Some(Err(e)) => {
+                self.parser.halt();
+                Err(e)
+            }
+            None => {
+                self.parser = parser.detach();
+                Ok(None)
+            }
+        }
+    }
+
+    /// Whether the whole note has been parsed.
+    pub fn is_done(&self) -> bool {
+        let state = self.current_state();
+        state == ParserState::Done
+            || (state == ParserState::ReadingTags && self.parser.tag_counts() == (0, 0))
+    }
+
+    /// Signal end of input.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Truncated`], annotated with the field that was cut
+    /// off, if the note is incomplete.
+    pub fn finish(&self) -> Result<(), Error> {
+        if self.is_done() {
+            Ok(())
+        } else {
+            Err(Error::Truncated.at(self.parser.offset(), self.parser.next_field()))
+        }
+    }
+
+    /// The current [`ParserState`].
+    pub fn current_state(&self) -> ParserState {
+        self.parser.current_state()
+    }
+
+    /// Bytes of the note parsed so far.
+    pub fn offset(&self) -> usize {
+        self.parser.offset()
+    }
+
+    /// Bytes buffered but not yet parsed.
+    ///
+    /// Once [`PushParser::is_done`], these are whatever followed the note.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+}
+
+/// The smallest buffered length at which parsing the field in `state` can
+/// possibly make progress, so retries are skipped until enough bytes arrive.
+fn min_needed(state: ParserState, elems_remaining: u64, input: &[u8]) -> usize {
+    let more = input.len() + 1;
+    match state {
+        ParserState::AfterVersion | ParserState::AfterId => 32,
+        ParserState::AfterPubkey => 64,
+        ParserState::AfterKind => length_prefixed(input, |len| len).unwrap_or(more),
+        ParserState::ReadingTags if elems_remaining > 0 => {
+            length_prefixed(input, |raw| raw >> 1).unwrap_or(more)
+        }
+        _ => more,
+    }
+}
+
+/// Total bytes for a varint length prefix plus its payload, if the prefix is complete.
+fn length_prefixed(input: &[u8], payload_len: impl Fn(u64) -> u64) -> Option<usize> {
+    let mut rest = input;
+    let raw = read_varint(&mut rest).ok()?;
+    let prefix = input.len() - rest.len();
+    usize::try_from(payload_len(raw))
+        .ok()
+        .map(|len| prefix.saturating_add(len).max(input.len() + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoteBuf, StringType, pack_note, test_util};
+
+    fn packed() -> Vec<u8> {
+        let note = NoteBuf {
+            created_at: 1_720_000_000,
+            kind: 30_000,
+            tags: vec![
+                vec!["p".into(), "aa".repeat(32), "wss://relay".into()],
+                vec![],
+                vec!["t".into(), "é".repeat(100)],
+            ],
+            content: "x".repeat(300),
+            ..test_util::note()
+        };
+        pack_note(&note).expect("pack")
+    }
+
+    fn describe(field: ParsedField<'_>) -> String {
+        match field {
+            ParsedField::Tag(StringType::Bytes(bs)) => format!("B:{}", hex::encode(bs)),
+            other => format!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn chunked_matches_whole_parse() -> Result<(), Error> {
+        let bytes = packed();
+        let expected: Vec<String> = NoteParser::new(&bytes)
+            .map(|f| f.map(describe))
+            .collect::<Result<_, _>>()?;
+
+        for chunk_size in [1, 2, 3, 7, 64, 1000] {
+            let mut parser = PushParser::new();
+            let mut got = Vec::new();
+            for chunk in bytes.chunks(chunk_size) {
+                parser.push(chunk);
+                while let Some(field) = parser.next_field()? {
+                    got.push(describe(field));
+                }
+            }
+            parser.finish()?;
+            assert_eq!(got, expected, "chunk size {chunk_size}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn id_is_available_before_rest_arrives() -> Result<(), Error> {
+        let bytes = packed();
+        let mut parser = PushParser::new();
+        parser.push(&bytes[..33]);
+
+        assert!(matches!(
+            parser.next_field()?,
+            Some(ParsedField::Version(1))
+        ));
+        match parser.next_field()? {
+            Some(ParsedField::Id(id)) => assert_eq!(id, &[0x01; 32]),
+            other => panic!("expected id, got {other:?}"),
+        }
+        assert!(parser.next_field()?.is_none());
+        assert!(!parser.is_done());
+        let err = parser.finish().unwrap_err();
+        assert!(matches!(err.root(), Error::Truncated));
+        assert_eq!(
+            err.to_string(),
+            "pubkey at offset 33: notepack string is truncated"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_input_errors_immediately() {
+        let mut bytes = packed();
+        // corrupt the content's first byte into invalid UTF-8
+        let content_start = bytes
+            .windows(300)
+            .position(|w| w.iter().all(|&b| b == b'x'))
+            .expect("content");
+        bytes[content_start] = 0xff;
+
+        let mut parser = PushParser::new();
+        parser.push(&bytes);
+        let err = loop {
+            match parser.next_field() {
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("expected an error"),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(err.root(), Error::Utf8(_)));
+        assert_eq!(err.offset(), Some(content_start - 2));
+        assert_eq!(err.field(), Some(crate::DecodeField::Content));
+        assert!(matches!(parser.next_field(), Ok(None)));
+    }
+
+    #[test]
+    fn limits_reject_oversized_fields_before_they_arrive() {
+        let bytes = packed();
+        let limits = DecodeLimits {
+            max_total_size: 200,
+            ..DecodeLimits::default()
+        };
+
+        let mut parser = PushParser::with_limits(limits);
+        // header + a few content bytes: the 300-byte content claim is already visible
+        parser.push(&bytes[..140]);
+        let err = loop {
+            match parser.next_field() {
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("expected an error"),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(err, Error::NoteTooLarge));
+    }
+}
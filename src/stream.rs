@@ -0,0 +1,487 @@
+use crate::parser::check_limit;
+use crate::varint::{read_varint, write_varint};
+use crate::{DecodeLimits, Error, Note, NoteBuf, NoteParser, pack_note};
+use std::io::{Read, Write};
+
+/// Magic bytes at the start of every framed notepack stream.
+pub const STREAM_MAGIC: [u8; 4] = *b"NPKS";
+
+/// Current framed stream version, written right after [`STREAM_MAGIC`].
+pub const STREAM_VERSION: u8 = 1;
+
+/// Writes notes as a framed notepack stream.
+///
+/// A stream is a header (`"NPKS"` + version byte) followed by any number of
+/// records, each a `varint(len)` followed by a `len`-byte notepack payload.
+/// See `SPEC.md` §15.
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, NoteReader, NoteWriter};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     ..NoteBuf::default()
+/// };
+///
+/// let mut writer = NoteWriter::new(Vec::new()).unwrap();
+/// writer.write_note(&note).unwrap();
+/// writer.write_note(&note).unwrap();
+/// let bytes = writer.into_inner();
+///
+/// let mut reader = NoteReader::new(bytes.as_slice()).unwrap();
+/// let mut count = 0;
+/// while let Some(note) = reader.next_note().unwrap() {
+///     assert_eq!(note.kind, 0);
+///     count += 1;
+/// }
+/// assert_eq!(count, 2);
+/// ```
+#[derive(Debug)]
+pub struct NoteWriter<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> NoteWriter<W> {
+    /// Create a writer and emit the stream header.
+    pub fn new(mut inner: W) -> Result<Self, Error> {
+        inner.write_all(&STREAM_MAGIC)?;
+        inner.write_all(&[STREAM_VERSION])?;
+        Ok(Self {
+            inner,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Pack `note` and append it as a record.
+    pub fn write_note(&mut self, note: &NoteBuf) -> Result<(), Error> {
+        let packed = pack_note(note)?;
+        self.write_packed(&packed)
+    }
+
+    /// Append an already-packed notepack payload as a record.
+    pub fn write_packed(&mut self, packed: &[u8]) -> Result<(), Error> {
+        self.buf.clear();
+        write_varint(&mut self.buf, packed.len() as u64);
+        self.inner.write_all(&self.buf)?;
+        self.inner.write_all(packed)?;
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.inner.flush()?)
+    }
+
+    /// Unwrap the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads records from a framed notepack stream written by [`NoteWriter`].
+///
+/// Records are read into a single reusable buffer, so each [`Note`] returned
+/// by [`NoteReader::next_note`] borrows from the reader and must be dropped
+/// before the next call.
+///
+/// Readers of untrusted streams should use [`NoteReader::with_limits`], since
+/// otherwise a peer can claim an arbitrarily large record and have it buffered
+/// until EOF.
+#[derive(Debug)]
+pub struct NoteReader<R: Read> {
+    inner: R,
+    buf: Vec<u8>,
+    limits: DecodeLimits,
+}
+
+impl<R: Read> NoteReader<R> {
+    /// Create a reader, consuming and validating the stream header.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidStreamHeader`] if the magic or version don't match.
+    pub fn new(inner: R) -> Result<Self, Error> {
+        Self::with_limits(inner, DecodeLimits::UNLIMITED)
+    }
+
+    /// Create a reader that enforces `limits` on every record.
+    ///
+    /// A record longer than [`DecodeLimits::max_total_size`] is rejected from
+    /// its length prefix, before any of it is buffered.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same [`Error`]s as [`NoteReader::new`].
+    pub fn with_limits(mut inner: R, limits: DecodeLimits) -> Result<Self, Error> {
+        let mut header = [0u8; STREAM_MAGIC.len() + 1];
+        read_exact_or_truncated(&mut inner, &mut header)?;
+        if header[..STREAM_MAGIC.len()] != STREAM_MAGIC
+            || header[STREAM_MAGIC.len()] != STREAM_VERSION
+        {
+            return Err(Error::InvalidStreamHeader);
+        }
+
+        Ok(Self {
+            inner,
+            buf: Vec::new(),
+            limits,
+        })
+    }
+
+    /// Read the next record's raw notepack payload.
+    ///
+    /// Returns `Ok(None)` at a clean end of stream. Running out of input in
+    /// the middle of a record is reported as [`Error::Truncated`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NoteTooLarge`] if the record's length prefix exceeds
+    /// [`DecodeLimits::max_total_size`].
+    pub fn next_packed(&mut self) -> Result<Option<&[u8]>, Error> {
+        let Some(len) = read_varint_io(&mut self.inner)? else {
+            return Ok(None);
+        };
+        check_limit(len, self.limits.max_total_size, Error::NoteTooLarge)?;
+
+        // Grow as bytes actually arrive rather than trusting `len` up front.
+        self.buf.clear();
+        let read = (&mut self.inner).take(len).read_to_end(&mut self.buf)?;
+        if (read as u64) < len {
+            return Err(Error::Truncated);
+        }
+
+        Ok(Some(&self.buf))
+    }
+
+    /// Read the next record and parse it into a zero-copy [`Note`].
+    pub fn next_note(&mut self) -> Result<Option<Note<'_>>, Error> {
+        let limits = self.limits;
+        match self.next_packed()? {
+            Some(packed) => Ok(Some(NoteParser::with_limits(packed, limits).into_note()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Unwrap the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// A zero-copy iterator over a framed notepack stream held in memory.
+///
+/// This is the slice counterpart of [`NoteReader`]: every [`Note`] borrows
+/// directly from the input (e.g. a memory-mapped file), so nothing is copied.
+/// Iteration stops after the first error. Streams from untrusted sources
+/// should be opened with [`NoteStream::with_limits`].
+///
+/// # Example
+///
+/// ```rust
+/// use notepack::{NoteBuf, NoteStream, NoteWriter};
+///
+/// let note = NoteBuf {
+///     id: "aa".repeat(32),
+///     pubkey: "bb".repeat(32),
+///     sig: "cc".repeat(64),
+///     ..NoteBuf::default()
+/// };
+///
+/// let mut writer = NoteWriter::new(Vec::new()).unwrap();
+/// writer.write_note(&note).unwrap();
+/// let bytes = writer.into_inner();
+///
+/// for note in NoteStream::new(&bytes).unwrap() {
+///     assert_eq!(note.unwrap().id, &[0xaa; 32]);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct NoteStream<'a> {
+    data: &'a [u8],
+    errored: bool,
+    limits: DecodeLimits,
+}
+
+impl<'a> NoteStream<'a> {
+    /// Validate the stream header and position at the first record.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidStreamHeader`] if the magic or version don't match.
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        Self::with_limits(data, DecodeLimits::UNLIMITED)
+    }
+
+    /// Like [`NoteStream::new`], but enforces `limits` on every record.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same [`Error`]s as [`NoteStream::new`].
+    pub fn with_limits(data: &'a [u8], limits: DecodeLimits) -> Result<Self, Error> {
+        let header_len = STREAM_MAGIC.len() + 1;
+        if data.len() < header_len {
+            return Err(Error::Truncated);
+        }
+        if data[..STREAM_MAGIC.len()] != STREAM_MAGIC || data[STREAM_MAGIC.len()] != STREAM_VERSION
+        {
+            return Err(Error::InvalidStreamHeader);
+        }
+
+        Ok(Self {
+            data: &data[header_len..],
+            errored: false,
+            limits,
+        })
+    }
+
+    /// Read the next record's raw notepack payload.
+    ///
+    /// Returns `Ok(None)` at a clean end of stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NoteTooLarge`] if the record's length prefix exceeds
+    /// [`DecodeLimits::max_total_size`].
+    pub fn next_packed(&mut self) -> Result<Option<&'a [u8]>, Error> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+
+        // the whole stream is in memory, so an unterminated length prefix
+        // can only mean the input was cut short
+        let len = read_varint(&mut self.data).map_err(|err| match err {
+            Error::VarintUnterminated => Error::Truncated,
+            err => err,
+        })?;
+        check_limit(len, self.limits.max_total_size, Error::NoteTooLarge)?;
+        if (self.data.len() as u64) < len {
+            return Err(Error::Truncated);
+        }
+        let (packed, rest) = self.data.split_at(len as usize);
+        self.data = rest;
+        Ok(Some(packed))
+    }
+}
+
+impl<'a> Iterator for NoteStream<'a> {
+    type Item = Result<Note<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.errored {
+            return None;
+        }
+
+        let item = match self.next_packed() {
+            Ok(Some(packed)) => NoteParser::with_limits(packed, self.limits).into_note(),
+            Ok(None) => return None,
+            Err(e) => Err(e),
+        };
+        self.errored = item.is_err();
+        Some(item)
+    }
+}
+
+fn read_exact_or_truncated(r: &mut impl Read, buf: &mut [u8]) -> Result<(), Error> {
+    r.read_exact(buf).map_err(|err| match err.kind() {
+        std::io::ErrorKind::UnexpectedEof => Error::Truncated,
+        _ => Error::Io(err),
+    })
+}
+
+/// Read a ULEB128 varint from `r`.
+///
+/// Returns `Ok(None)` if the reader is at EOF before the first byte, and
+/// [`Error::Truncated`] if it hits EOF after that.
+fn read_varint_io(r: &mut impl Read) -> Result<Option<u64>, Error> {
+    let mut n = 0u64;
+    let mut shift = 0u32;
+    let mut first = true;
+
+    loop {
+        let mut byte = [0u8; 1];
+        match r.read(&mut byte) {
+            Ok(0) if first => return Ok(None),
+            Ok(0) => return Err(Error::Truncated),
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::Io(err)),
+        }
+        first = false;
+
+        let b = byte[0];
+        n |= ((b & 0x7F) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok(Some(n));
+        }
+
+        shift += 7;
+        if shift >= 64 {
+            return Err(Error::VarintOverflow);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn note(kind: u64, content: &str) -> NoteBuf {
+        NoteBuf {
+            kind,
+            content: content.into(),
+            tags: vec![vec!["p".into(), "04".repeat(32)]],
+            ..test_util::note()
+        }
+    }
+
+    #[test]
+    fn writer_reader_round_trip() -> Result<(), Error> {
+        let mut writer = NoteWriter::new(Vec::new())?;
+        for i in 0..3 {
+            writer.write_note(&note(i, &"x".repeat(i as usize * 100)))?;
+        }
+        let bytes = writer.into_inner();
+        assert_eq!(&bytes[..4], b"NPKS");
+
+        let mut reader = NoteReader::new(bytes.as_slice())?;
+        for i in 0..3 {
+            let n = reader.next_note()?.expect("note");
+            assert_eq!(n.kind, i);
+            assert_eq!(n.content.len(), i as usize * 100);
+        }
+        assert!(reader.next_note()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn slice_stream_matches_reader() -> Result<(), Error> {
+        let mut writer = NoteWriter::new(Vec::new())?;
+        for i in 0..3 {
+            writer.write_note(&note(i, "hi"))?;
+        }
+        let bytes = writer.into_inner();
+
+        let kinds: Vec<u64> = NoteStream::new(&bytes)?
+            .map(|n| n.map(|n| n.kind))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(kinds, vec![0, 1, 2]);
+
+        let mut truncated = NoteStream::new(&bytes[..bytes.len() - 1])?;
+        assert!(truncated.next().expect("first").is_ok());
+        assert!(truncated.next().expect("second").is_ok());
+        assert!(matches!(truncated.next(), Some(Err(Error::Truncated))));
+        assert!(truncated.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn reader_rejects_bad_header() {
+        let err = NoteReader::new(&b"NOPE\x01"[..]).unwrap_err();
+        assert!(matches!(err, Error::InvalidStreamHeader));
+
+        let err = NoteReader::new(&b"NPKS\x02"[..]).unwrap_err();
+        assert!(matches!(err, Error::InvalidStreamHeader));
+
+        let err = NoteReader::new(&b"NP"[..]).unwrap_err();
+        assert!(matches!(err, Error::Truncated));
+    }
+
+    #[test]
+    fn reader_reports_truncated_record() -> Result<(), Error> {
+        let mut writer = NoteWriter::new(Vec::new())?;
+        writer.write_note(&note(1, "hello"))?;
+        let mut bytes = writer.into_inner();
+        bytes.truncate(bytes.len() - 3);
+
+        let mut reader = NoteReader::new(bytes.as_slice())?;
+        assert!(matches!(reader.next_packed(), Err(Error::Truncated)));
+        Ok(())
+    }
+
+    #[test]
+    fn reader_reports_truncated_length_prefix() -> Result<(), Error> {
+        let mut writer = NoteWriter::new(Vec::new())?;
+        writer.write_note(&note(1, &"x".repeat(200)))?;
+        let bytes = writer.into_inner();
+
+        // the record length needs a two-byte varint; keep only the first
+        let header_len = STREAM_MAGIC.len() + 1;
+        assert!(bytes[header_len] & 0x80 != 0);
+        let mut reader = NoteReader::new(&bytes[..header_len + 1])?;
+        assert!(matches!(reader.next_packed(), Err(Error::Truncated)));
+
+        let mut stream = NoteStream::new(&bytes[..header_len + 1])?;
+        assert!(matches!(stream.next_packed(), Err(Error::Truncated)));
+        Ok(())
+    }
+
+    #[test]
+    fn reader_rejects_records_over_the_size_limit() -> Result<(), Error> {
+        let mut writer = NoteWriter::new(Vec::new())?;
+        writer.write_note(&note(1, "short"))?;
+        writer.write_note(&note(2, &"x".repeat(1000)))?;
+        let bytes = writer.into_inner();
+
+        let limits = DecodeLimits {
+            max_total_size: 500,
+            ..DecodeLimits::default()
+        };
+        let mut reader = NoteReader::with_limits(bytes.as_slice(), limits)?;
+        assert_eq!(reader.next_note()?.expect("first").kind, 1);
+        assert!(matches!(reader.next_packed(), Err(Error::NoteTooLarge)));
+
+        // a claimed length is rejected before anything is buffered
+        let mut bytes = Vec::from(STREAM_MAGIC);
+        bytes.push(STREAM_VERSION);
+        write_varint(&mut bytes, u64::MAX >> 1);
+        let mut reader = NoteReader::with_limits(bytes.as_slice(), limits)?;
+        assert!(matches!(reader.next_packed(), Err(Error::NoteTooLarge)));
+        Ok(())
+    }
+
+    #[test]
+    fn stream_enforces_limits() -> Result<(), Error> {
+        let mut writer = NoteWriter::new(Vec::new())?;
+        writer.write_note(&note(1, "short"))?;
+        writer.write_note(&note(2, &"x".repeat(1000)))?;
+        let bytes = writer.into_inner();
+
+        let limits = DecodeLimits {
+            max_total_size: 500,
+            ..DecodeLimits::default()
+        };
+        let mut stream = NoteStream::with_limits(&bytes, limits)?;
+        assert_eq!(stream.next().expect("first")?.kind, 1);
+        assert!(matches!(stream.next(), Some(Err(Error::NoteTooLarge))));
+        assert!(stream.next().is_none());
+
+        let limits = DecodeLimits {
+            max_content_bytes: 100,
+            ..DecodeLimits::default()
+        };
+        let err = NoteStream::with_limits(&bytes, limits)?
+            .nth(1)
+            .expect("second")
+            .unwrap_err();
+        assert!(matches!(err.root(), Error::ContentTooLarge));
+        Ok(())
+    }
+
+    #[test]
+    fn reader_does_not_preallocate_claimed_length() -> Result<(), Error> {
+        let mut bytes = Vec::from(STREAM_MAGIC);
+        bytes.push(STREAM_VERSION);
+        write_varint(&mut bytes, u64::MAX >> 1);
+        bytes.extend_from_slice(b"tiny");
+
+        let mut reader = NoteReader::new(bytes.as_slice())?;
+        assert!(matches!(reader.next_packed(), Err(Error::Truncated)));
+        Ok(())
+    }
+}
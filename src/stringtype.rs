@@ -3,3 +3,29 @@ pub enum StringType<'a> {
     Bytes(&'a [u8]),
     Str(&'a str),
 }
+
+/// An owned [`StringType`], e.g. a tag element of a [`BinaryNote`](crate::BinaryNote).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StringTypeBuf {
+    Bytes(Vec<u8>),
+    Str(String),
+}
+
+impl StringTypeBuf {
+    /// Borrow as a [`StringType`].
+    pub fn as_string_type(&self) -> StringType<'_> {
+        match self {
+            StringTypeBuf::Bytes(bs) => StringType::Bytes(bs),
+            StringTypeBuf::Str(s) => StringType::Str(s),
+        }
+    }
+}
+
+impl From<StringType<'_>> for StringTypeBuf {
+    fn from(elem: StringType<'_>) -> Self {
+        match elem {
+            StringType::Bytes(bs) => StringTypeBuf::Bytes(bs.to_vec()),
+            StringType::Str(s) => StringTypeBuf::Str(s.to_string()),
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use crate::NoteBuf;
+
+/// A note with valid fixed fields (id `01..`, pubkey `02..`, sig `03..`)
+/// and everything else empty, for tests to fill in with struct update syntax.
+pub(crate) fn note() -> NoteBuf {
+    NoteBuf {
+        id: "01".repeat(32),
+        pubkey: "02".repeat(32),
+        sig: "03".repeat(64),
+        ..NoteBuf::default()
+    }
+}
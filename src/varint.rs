@@ -17,6 +17,25 @@ pub fn write_varint(buf: &mut Vec<u8>, mut n: u64) -> usize {
     len
 }
 
+/// Encode `n` into `buf`, returning the bytes used.
+pub fn encode_varint(buf: &mut [u8; 10], mut n: u64) -> &[u8] {
+    let mut len = 0;
+    loop {
+        let b = (n & 0x7F) as u8;
+        n >>= 7;
+        buf[len] = if n != 0 { b | 0x80 } else { b };
+        len += 1;
+        if n == 0 {
+            return &buf[..len];
+        }
+    }
+}
+
+/// Number of bytes [`write_varint`] uses for `n`.
+pub fn varint_len(n: u64) -> usize {
+    (64 - n.max(1).leading_zeros() as usize).div_ceil(7)
+}
+
 pub fn read_varint(input: &mut &[u8]) -> Result<u64, Error> {
     let mut n = 0u64;
     let mut shift = 0u32;
@@ -39,11 +58,25 @@ pub fn read_varint(input: &mut &[u8]) -> Result<u64, Error> {
     Err(Error::VarintUnterminated)
 }
 
-pub fn read_tagged_varint(input: &mut &[u8]) -> Result<(u64, bool), Error> {
-    let raw = read_varint(input)?;
-    Ok((raw >> 1, (raw & 1) != 0))
+/// Like [`read_varint`], but only accepts the shortest encoding of each value.
+///
+/// Rejects trailing zero groups (e.g. `0x80 0x00` for zero) with
+/// [`Error::NonCanonicalVarint`], and a tenth byte carrying bits past 64 with
+/// [`Error::VarintOverflow`].
+pub fn read_canonical_varint(input: &mut &[u8]) -> Result<u64, Error> {
+    let start = *input;
+    let n = read_varint(input)?;
+    let used = start.len() - input.len();
+    if used > 1 && start[used - 1] == 0 {
+        return Err(Error::NonCanonicalVarint);
+    }
+    if used == 10 && start[9] > 1 {
+        return Err(Error::VarintOverflow);
+    }
+    Ok(n)
 }
 
+#[cfg(test)]
 pub fn write_tagged_varint(buf: &mut Vec<u8>, value: u64, tagged: bool) -> usize {
     let tagged = value
         .checked_shl(1)